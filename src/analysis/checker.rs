@@ -0,0 +1,556 @@
+use super::super::compiler::diagnostic::Diagnostic;
+use super::super::compiler::lexer::Token;
+use super::super::compiler::parser::{ExpressionAST, LiteralAST, PipelineAST, AST};
+use super::super::runtime::builtins::functions::function::build_function_map;
+use super::super::runtime::builtins::pipelines::pipeline::build_pipeline_parameter_map;
+use super::super::runtime::builtins::variables::variable::build_variable_map;
+use super::super::runtime::function::Function;
+use super::super::runtime::imported_pipeline::{
+	resolve_import_path, ImportError, ImportedPipeline,
+};
+use super::super::runtime::pipeline::PipelineParameter;
+use super::super::runtime::value::ValueType;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+pub struct Checker {
+	function_map: HashMap<String, Box<dyn Function + Send + Sync>>,
+	pipeline_parameter_map: HashMap<String, Vec<PipelineParameter>>,
+	predefined_variable_set: HashSet<String>,
+	// Pipelines checked so far, with the signature of those that declare one.
+	checked_path_map: HashMap<PathBuf, Option<Vec<PipelineParameter>>>,
+	// Pipelines being checked, from the entry to the current one.
+	import_stack: Vec<PathBuf>,
+	src_content_map: HashMap<String, Vec<String>>,
+	diagnostic_vec: Vec<Diagnostic>,
+}
+
+struct Scope<'pipeline> {
+	pipeline: &'pipeline ImportedPipeline,
+	check_variables: bool,
+	variable_set: HashSet<String>,
+	// Pipelines visible in this scope; None if the signature is unknown or not declared.
+	pipeline_map: HashMap<String, Option<Vec<PipelineParameter>>>,
+	// Imported pipelines that import this one, directly or not, by name; using them recurses.
+	circular_import_map: HashMap<String, PathBuf>,
+	loop_depth: usize,
+}
+
+impl Checker {
+	pub fn new() -> Checker {
+		Checker {
+			function_map: build_function_map(),
+			pipeline_parameter_map: build_pipeline_parameter_map(),
+			predefined_variable_set: build_variable_map().into_keys().collect(),
+			checked_path_map: HashMap::new(),
+			import_stack: Vec::new(),
+			src_content_map: HashMap::new(),
+			diagnostic_vec: Vec::new(),
+		}
+	}
+
+	pub fn diagnostic_vec(&self) -> &Vec<Diagnostic> {
+		&self.diagnostic_vec
+	}
+
+	pub fn src_content(&self, file_path: &str) -> &[String] {
+		self.src_content_map
+			.get(file_path)
+			.map(|src_content| src_content.as_slice())
+			.unwrap_or(&[])
+	}
+
+	// Checks the entry pipeline and every pipeline it imports, without executing anything.
+	pub fn check_entry(&mut self, path: &Path) -> Result<(), ImportError> {
+		let pipeline = ImportedPipeline::import(path)?;
+		self.checked_path_map.insert(pipeline.path().clone(), None);
+		self.check_pipeline(&pipeline, true);
+		Ok(())
+	}
+
+	fn check_import(&mut self, token: &Token, path: PathBuf) -> Option<Vec<PipelineParameter>> {
+		if let Some(parameter_vec) = self.checked_path_map.get(&path) {
+			return parameter_vec.clone();
+		}
+
+		self.checked_path_map.insert(path.clone(), None);
+
+		let parameter_vec = match ImportedPipeline::import(&path) {
+			Ok(pipeline) => {
+				self.check_pipeline(&pipeline, false);
+
+				if pipeline.parameter_vec().is_empty() {
+					None
+				} else {
+					Some(pipeline.parameter_vec().clone())
+				}
+			}
+			Err(ImportError::Malformed(diagnostic_vec, src_content)) => {
+				if let Some(diagnostic) = diagnostic_vec.first() {
+					self.src_content_map
+						.insert(diagnostic.file_path.clone(), src_content);
+				}
+
+				self.diagnostic_vec.extend(diagnostic_vec);
+				None
+			}
+			Err(err) => {
+				self.report(
+					token,
+					&format!("Unable to import '{}'; {}", path.display(), err),
+				);
+				None
+			}
+		};
+
+		self.checked_path_map.insert(path, parameter_vec.clone());
+		parameter_vec
+	}
+
+	fn check_pipeline(&mut self, pipeline: &ImportedPipeline, is_entry: bool) {
+		self.src_content_map.insert(
+			pipeline.path().to_string_lossy().into_owned(),
+			pipeline.src_content().clone(),
+		);
+
+		let mut variable_set = self.predefined_variable_set.clone();
+
+		// Arguments are injected as variables, so they can only be tracked if the pipeline
+		// declares its parameters. Names tested by is_exists are defined conditionally.
+		collect_is_exists_names(pipeline.ast_vec(), &mut variable_set);
+
+		let mut scope = Scope {
+			pipeline,
+			check_variables: is_entry || !pipeline.parameter_vec().is_empty(),
+			variable_set,
+			pipeline_map: self
+				.pipeline_parameter_map
+				.iter()
+				.map(|(name, parameter_vec)| (name.clone(), Some(parameter_vec.clone())))
+				.collect(),
+			circular_import_map: HashMap::new(),
+			loop_depth: 0,
+		};
+
+		self.import_stack.push(pipeline.path().clone());
+		self.check_ast_vec(&mut scope, pipeline.ast_vec());
+		self.import_stack.pop();
+	}
+
+	fn check_ast_vec(&mut self, scope: &mut Scope, ast_vec: &[AST]) {
+		for ast in ast_vec.iter() {
+			match ast {
+				AST::Import(import_ast) => {
+					self.check_expression(scope, &import_ast.path);
+
+					let parameter_vec = match &import_ast.path {
+						ExpressionAST::Literal(LiteralAST::String(path_token)) => {
+							match resolve_import_path(
+								scope.pipeline.path(),
+								&path_token.token_content,
+							) {
+								Ok(path) if self.import_stack.contains(&path) => {
+									scope
+										.circular_import_map
+										.insert(import_ast.name.token_content.clone(), path);
+									None
+								}
+								Ok(path) => self.check_import(path_token, path),
+								Err(err) => {
+									self.report(
+										path_token,
+										&format!(
+											"Unable to resolve '{}'; {}.",
+											path_token.token_content, err
+										),
+									);
+									None
+								}
+							}
+						}
+						_ => None,
+					};
+
+					scope
+						.pipeline_map
+						.insert(import_ast.name.token_content.clone(), parameter_vec);
+				}
+				AST::Param(param_ast) => {
+					if let Some(default) = &param_ast.default {
+						self.check_expression(scope, default);
+					}
+
+					scope
+						.variable_set
+						.insert(param_ast.name.token_content.clone());
+				}
+				AST::Set(set_ast) | AST::Export(set_ast) => {
+					self.check_expression(scope, &set_ast.value);
+					scope
+						.variable_set
+						.insert(set_ast.name.token_content.clone());
+				}
+				AST::Print(print_ast) => {
+					for expression_ast in print_ast.expression_vec.iter() {
+						self.check_expression(scope, expression_ast);
+					}
+				}
+				AST::PrintErr(print_err_ast) => {
+					for expression_ast in print_err_ast.expression_vec.iter() {
+						self.check_expression(scope, expression_ast);
+					}
+				}
+				AST::Return(return_ast) => {
+					if let Some(value) = &return_ast.value {
+						self.check_expression(scope, value);
+					}
+				}
+				AST::Await(..) | AST::AwaitAll => {}
+				AST::NonBlock(non_block_ast) => {
+					self.check_pipeline_invocation(scope, &non_block_ast.pipeline);
+				}
+				AST::For(for_ast) => {
+					self.check_expression(scope, &for_ast.variable_in);
+					scope
+						.variable_set
+						.insert(for_ast.variable_name.token_content.clone());
+					scope.loop_depth += 1;
+					self.check_ast_vec(scope, &for_ast.body_ast_vec);
+					scope.loop_depth -= 1;
+				}
+				AST::Break(token) => {
+					if scope.loop_depth == 0 {
+						self.report(token, "A break statement only can be used in a loop.");
+					}
+				}
+				AST::Continue(token) => {
+					if scope.loop_depth == 0 {
+						self.report(token, "A continue statement only can be used in a loop.");
+					}
+				}
+				AST::If(if_ast) => {
+					self.check_expression(scope, &if_ast.criteria);
+					self.check_ast_vec(scope, &if_ast.if_ast_vec);
+
+					if let Some(else_ast_vec) = &if_ast.else_ast_vec {
+						self.check_ast_vec(scope, else_ast_vec);
+					}
+				}
+				AST::Assert(assert_ast) => {
+					self.check_expression(scope, &assert_ast.criteria);
+
+					if let Some(message) = &assert_ast.message {
+						self.check_expression(scope, message);
+					}
+				}
+				AST::Test(test_ast) => {
+					self.check_ast_vec(scope, &test_ast.body_ast_vec);
+				}
+				AST::Pipeline(pipeline_ast) => {
+					self.check_pipeline_invocation(scope, pipeline_ast);
+				}
+				AST::Call(call_ast) => {
+					self.check_call(scope, &call_ast.name, &call_ast.argument_vec);
+				}
+			}
+		}
+	}
+
+	fn check_pipeline_invocation(&mut self, scope: &mut Scope, pipeline_ast: &PipelineAST) {
+		for modifier_ast in pipeline_ast.retry.iter().chain(pipeline_ast.timeout.iter()) {
+			self.check_expression(scope, &modifier_ast.value);
+		}
+
+		for (_, expression_ast) in pipeline_ast.argument_vec.iter() {
+			self.check_expression(scope, expression_ast);
+		}
+
+		self.check_circular_import(scope, &pipeline_ast.name);
+
+		match scope.pipeline_map.get(&pipeline_ast.name.token_content) {
+			Some(Some(parameter_vec)) => {
+				let parameter_vec = parameter_vec.clone();
+				self.check_argument_vec(pipeline_ast, &parameter_vec);
+			}
+			Some(None) => {}
+			None => self.report(
+				&pipeline_ast.name,
+				&format!(
+					"Undefined pipeline '{}'; import it first.",
+					pipeline_ast.name.token_content
+				),
+			),
+		}
+
+		if let Some(result_as) = &pipeline_ast.result_as {
+			scope.variable_set.insert(result_as.token_content.clone());
+		}
+	}
+
+	// The runtime fails once a pipeline is used while it is being executed through its own import.
+	fn check_circular_import(&mut self, scope: &Scope, name: &Token) {
+		if let Some(path) = scope.circular_import_map.get(&name.token_content) {
+			self.report(
+				name,
+				&format!(
+					"Circular import of '{}'; it imports this pipeline, directly or through others.",
+					path.display()
+				),
+			);
+		}
+	}
+
+	fn check_argument_vec(
+		&mut self,
+		pipeline_ast: &PipelineAST,
+		parameter_vec: &[PipelineParameter],
+	) {
+		for (name, expression_ast) in pipeline_ast.argument_vec.iter() {
+			match parameter_vec
+				.iter()
+				.find(|parameter| parameter.name == name.token_content)
+			{
+				Some(parameter) => {
+					if let Some(value_type) = static_value_type(expression_ast) {
+						if value_type != parameter.value_type {
+							self.report(
+								name,
+								&format!(
+									"'{}' must be a '{:#?}' type, got '{:#?}'.",
+									parameter.name, parameter.value_type, value_type
+								),
+							);
+						}
+					}
+				}
+				None => self.report(
+					name,
+					&format!(
+						"Unknown argument '{}' for pipeline '{}'.",
+						name.token_content, pipeline_ast.name.token_content
+					),
+				),
+			}
+		}
+
+		for parameter in parameter_vec.iter() {
+			if parameter.required
+				&& !pipeline_ast
+					.argument_vec
+					.iter()
+					.any(|(name, _)| name.token_content == parameter.name)
+			{
+				self.report(
+					&pipeline_ast.name,
+					&format!("Missing required argument '{}'.", parameter.name),
+				);
+			}
+		}
+	}
+
+	fn check_call(&mut self, scope: &mut Scope, name: &Token, argument_vec: &[ExpressionAST]) {
+		for expression_ast in argument_vec.iter() {
+			self.check_expression(scope, expression_ast);
+		}
+
+		let argument_count = match self.function_map.get(&name.token_content) {
+			Some(function) => function.argument_count(),
+			None => {
+				self.report(
+					name,
+					&format!("Undefined function '{}'.", name.token_content),
+				);
+				return;
+			}
+		};
+
+		if let Some(argument_count) = argument_count {
+			if argument_count != argument_vec.len() {
+				self.report(
+					name,
+					&format!(
+						"'{}' takes {} argument{}, got {}.",
+						name.token_content,
+						argument_count,
+						if argument_count == 1 { "" } else { "s" },
+						argument_vec.len()
+					),
+				);
+			}
+		}
+	}
+
+	fn check_expression(&mut self, scope: &mut Scope, expression_ast: &ExpressionAST) {
+		match expression_ast {
+			ExpressionAST::Array(array) => {
+				for element in array.iter() {
+					self.check_expression(scope, element);
+				}
+			}
+			ExpressionAST::Dictionary(dictionary) => {
+				for (_, (_, value)) in dictionary.iter() {
+					self.check_expression(scope, value);
+				}
+			}
+			ExpressionAST::Literal(..) => {}
+			ExpressionAST::Variable(token) => {
+				if scope.check_variables && !scope.variable_set.contains(&token.token_content) {
+					self.report(
+						token,
+						&format!("Undefined variable '{}'.", token.token_content),
+					);
+				}
+			}
+			ExpressionAST::Member(member_ast) => {
+				self.check_circular_import(scope, &member_ast.module);
+
+				if !scope
+					.pipeline_map
+					.contains_key(&member_ast.module.token_content)
+				{
+					self.report(
+						&member_ast.module,
+						&format!(
+							"Undefined pipeline '{}'; import it first.",
+							member_ast.module.token_content
+						),
+					);
+				}
+			}
+			ExpressionAST::Call(call_ast) => {
+				self.check_call(scope, &call_ast.name, &call_ast.argument_vec);
+			}
+		}
+	}
+
+	fn report(&mut self, token: &Token, message: &str) {
+		self.diagnostic_vec.push(Diagnostic::new(token, message));
+	}
+}
+
+fn static_value_type(expression_ast: &ExpressionAST) -> Option<ValueType> {
+	match expression_ast {
+		ExpressionAST::Array(..) => Some(ValueType::Array),
+		ExpressionAST::Dictionary(..) => Some(ValueType::Dictionary),
+		ExpressionAST::Literal(LiteralAST::Bool(..)) => Some(ValueType::Bool),
+		ExpressionAST::Literal(LiteralAST::Integer(..)) => Some(ValueType::Integer),
+		ExpressionAST::Literal(LiteralAST::String(..)) => Some(ValueType::String),
+		ExpressionAST::Variable(..) | ExpressionAST::Member(..) | ExpressionAST::Call(..) => None,
+	}
+}
+
+fn collect_is_exists_names(ast_vec: &[AST], name_set: &mut HashSet<String>) {
+	for ast in ast_vec.iter() {
+		match ast {
+			AST::If(if_ast) => {
+				collect_is_exists_names_in_expression(&if_ast.criteria, name_set);
+				collect_is_exists_names(&if_ast.if_ast_vec, name_set);
+
+				if let Some(else_ast_vec) = &if_ast.else_ast_vec {
+					collect_is_exists_names(else_ast_vec, name_set);
+				}
+			}
+			AST::For(for_ast) => {
+				collect_is_exists_names(&for_ast.body_ast_vec, name_set);
+			}
+			AST::Set(set_ast) | AST::Export(set_ast) => {
+				collect_is_exists_names_in_expression(&set_ast.value, name_set);
+			}
+			_ => {}
+		}
+	}
+}
+
+fn collect_is_exists_names_in_expression(
+	expression_ast: &ExpressionAST,
+	name_set: &mut HashSet<String>,
+) {
+	if let ExpressionAST::Call(call_ast) = expression_ast {
+		if call_ast.name.token_content == "is_exists" {
+			if let Some(ExpressionAST::Literal(LiteralAST::String(name))) =
+				call_ast.argument_vec.first()
+			{
+				name_set.insert(name.token_content.clone());
+			}
+		}
+
+		for argument in call_ast.argument_vec.iter() {
+			collect_is_exists_names_in_expression(argument, name_set);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::fs::{create_dir_all, remove_dir_all, write};
+	use std::process;
+
+	#[test]
+	fn checks_arguments_of_every_import_of_a_pipeline() {
+		let directory = std::env::temp_dir().join(format!("piped-checker-{}", process::id()));
+		create_dir_all(&directory).unwrap();
+		write(directory.join("lib.piped"), "@param x: Integer;\n").unwrap();
+		write(
+			directory.join("a.piped"),
+			"@import \"./lib.piped\" as lib;\nlib x=1;\n",
+		)
+		.unwrap();
+		write(
+			directory.join("main.piped"),
+			"@import \"./a.piped\" as a;\n@import \"./lib.piped\" as lib;\na;\nlib y=1;\n",
+		)
+		.unwrap();
+
+		let mut checker = Checker::new();
+		checker.check_entry(&directory.join("main.piped")).ok();
+		let message_vec = checker
+			.diagnostic_vec()
+			.iter()
+			.map(|diagnostic| diagnostic.message.clone())
+			.collect::<Vec<_>>();
+		remove_dir_all(&directory).unwrap();
+
+		assert_eq!(
+			message_vec,
+			vec![
+				"Unknown argument 'y' for pipeline 'lib'.".to_owned(),
+				"Missing required argument 'x'.".to_owned(),
+			]
+		);
+	}
+
+	#[test]
+	fn reports_circular_imports_that_are_used() {
+		let directory = std::env::temp_dir().join(format!("piped-cycle-{}", process::id()));
+		create_dir_all(&directory).unwrap();
+		write(
+			directory.join("a.piped"),
+			"@import \"./b.piped\" as b;\nb;\n",
+		)
+		.unwrap();
+		write(
+			directory.join("b.piped"),
+			"@import \"./a.piped\" as a;\n@import \"./main.piped\" as main;\na;\n",
+		)
+		.unwrap();
+		write(
+			directory.join("main.piped"),
+			"@import \"./a.piped\" as a;\na;\n",
+		)
+		.unwrap();
+
+		let mut checker = Checker::new();
+		checker.check_entry(&directory.join("main.piped")).ok();
+		let diagnostic_vec = checker
+			.diagnostic_vec()
+			.iter()
+			.map(|diagnostic| (diagnostic.file_path.clone(), diagnostic.message.clone()))
+			.collect::<Vec<_>>();
+		remove_dir_all(&directory).unwrap();
+
+		assert_eq!(diagnostic_vec.len(), 1);
+		assert!(diagnostic_vec[0].0.ends_with("b.piped"));
+		assert!(diagnostic_vec[0].1.starts_with("Circular import of '"));
+	}
+}
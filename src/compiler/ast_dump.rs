@@ -0,0 +1,210 @@
+use super::diagnostic::Diagnostic;
+use super::lexer::Token;
+use super::lookahead_lexer::LookaheadLexer as Lexer;
+use super::parser::{parse, CallAST, ExpressionAST, LiteralAST, ModifierAST, PipelineAST, AST};
+use serde_json::{json, Value};
+
+// Bump this whenever the shape of the dump changes, so that tools can detect it.
+const SCHEMA_VERSION: u64 = 4;
+
+pub fn dump(file_path: &str, content: &str) -> Result<String, Vec<Diagnostic>> {
+    let ast_vec = parse(&mut Lexer::new(file_path.to_owned(), content.to_owned()))?;
+    let root = json!({
+        "version": SCHEMA_VERSION,
+        "file": file_path,
+        "statements": dump_statement_vec(&ast_vec),
+    });
+
+    Ok(serde_json::to_string_pretty(&root).unwrap())
+}
+
+fn dump_token(token: &Token) -> Value {
+    json!({
+        "content": token.token_content,
+        "line": token.line_number,
+        "offset": token.line_offset,
+    })
+}
+
+fn dump_optional_token(token: &Option<Token>) -> Value {
+    token.as_ref().map_or(Value::Null, dump_token)
+}
+
+fn dump_optional_expression(expression_ast: &Option<ExpressionAST>) -> Value {
+    expression_ast.as_ref().map_or(Value::Null, dump_expression)
+}
+
+fn dump_statement_vec(ast_vec: &[AST]) -> Value {
+    Value::Array(ast_vec.iter().map(dump_statement).collect())
+}
+
+fn dump_statement(ast: &AST) -> Value {
+    match ast {
+        AST::Import(import_ast) => json!({
+            "kind": "import",
+            "name": dump_token(&import_ast.name),
+            "path": dump_expression(&import_ast.path),
+        }),
+        AST::Param(param_ast) => json!({
+            "kind": "param",
+            "name": dump_token(&param_ast.name),
+            "type": dump_token(&param_ast.value_type),
+            "default": dump_optional_expression(&param_ast.default),
+        }),
+        AST::Set(set_ast) => json!({
+            "kind": "set",
+            "name": dump_token(&set_ast.name),
+            "value": dump_expression(&set_ast.value),
+        }),
+        AST::Export(export_ast) => json!({
+            "kind": "export",
+            "name": dump_token(&export_ast.name),
+            "value": dump_expression(&export_ast.value),
+        }),
+        AST::Print(print_ast) => json!({
+            "kind": "print",
+            "values": print_ast.expression_vec.iter().map(dump_expression).collect::<Vec<_>>(),
+        }),
+        AST::PrintErr(print_err_ast) => json!({
+            "kind": "printErr",
+            "values": print_err_ast
+                .expression_vec
+                .iter()
+                .map(dump_expression)
+                .collect::<Vec<_>>(),
+        }),
+        AST::Return(return_ast) => json!({
+            "kind": "return",
+            "value": dump_optional_expression(&return_ast.value),
+        }),
+        AST::Await(await_ast) => json!({
+            "kind": "await",
+            "name": dump_optional_token(&await_ast.name),
+        }),
+        AST::AwaitAll => json!({ "kind": "awaitAll" }),
+        AST::NonBlock(non_block_ast) => json!({
+            "kind": "nonblock",
+            "name": dump_optional_token(&non_block_ast.name),
+            "pipeline": dump_pipeline(&non_block_ast.pipeline),
+        }),
+        AST::For(for_ast) => json!({
+            "kind": "for",
+            "variable": dump_token(&for_ast.variable_name),
+            "in": dump_expression(&for_ast.variable_in),
+            "body": dump_statement_vec(&for_ast.body_ast_vec),
+        }),
+        AST::Break(token) => json!({ "kind": "break", "token": dump_token(token) }),
+        AST::Continue(token) => json!({ "kind": "continue", "token": dump_token(token) }),
+        AST::If(if_ast) => json!({
+            "kind": "if",
+            "criteria": dump_expression(&if_ast.criteria),
+            "then": dump_statement_vec(&if_ast.if_ast_vec),
+            "else": if_ast
+                .else_ast_vec
+                .as_ref()
+                .map_or(Value::Null, |else_ast_vec| dump_statement_vec(else_ast_vec)),
+        }),
+        AST::Assert(assert_ast) => json!({
+            "kind": "assert",
+            "token": dump_token(&assert_ast.token),
+            "criteria": dump_expression(&assert_ast.criteria),
+            "message": dump_optional_expression(&assert_ast.message),
+        }),
+        AST::Test(test_ast) => json!({
+            "kind": "test",
+            "name": dump_token(&test_ast.name),
+            "body": dump_statement_vec(&test_ast.body_ast_vec),
+        }),
+        AST::Pipeline(pipeline_ast) => dump_pipeline(pipeline_ast),
+        AST::Call(call_ast) => dump_call(call_ast),
+    }
+}
+
+fn dump_pipeline(pipeline_ast: &PipelineAST) -> Value {
+    json!({
+        "kind": "pipeline",
+        "name": dump_token(&pipeline_ast.name),
+        "arguments": pipeline_ast
+            .argument_vec
+            .iter()
+            .map(|(name, value)| json!({
+                "name": dump_token(name),
+                "value": dump_expression(value),
+            }))
+            .collect::<Vec<_>>(),
+        "resultAs": dump_optional_token(&pipeline_ast.result_as),
+        "retry": pipeline_ast.retry.as_ref().map(dump_modifier),
+        "timeout": pipeline_ast.timeout.as_ref().map(dump_modifier),
+    })
+}
+
+fn dump_modifier(modifier_ast: &ModifierAST) -> Value {
+    json!({
+        "keyword": dump_token(&modifier_ast.keyword),
+        "value": dump_expression(&modifier_ast.value),
+    })
+}
+
+fn dump_call(call_ast: &CallAST) -> Value {
+    json!({
+        "kind": "call",
+        "name": dump_token(&call_ast.name),
+        "arguments": call_ast.argument_vec.iter().map(dump_expression).collect::<Vec<_>>(),
+    })
+}
+
+fn dump_expression(expression_ast: &ExpressionAST) -> Value {
+    match expression_ast {
+        ExpressionAST::Array(element_vec) => json!({
+            "kind": "array",
+            "elements": element_vec.iter().map(dump_expression).collect::<Vec<_>>(),
+        }),
+        ExpressionAST::Dictionary(dictionary) => {
+            // The parser keeps entries in a map; restore the written order for a stable output.
+            let mut entry_vec = dictionary.values().collect::<Vec<_>>();
+            entry_vec.sort_by_key(|(key, _)| (key.line_number, key.line_offset));
+
+            json!({
+                "kind": "dictionary",
+                "entries": entry_vec
+                    .into_iter()
+                    .map(|(key, value)| json!({
+                        "key": dump_token(key),
+                        "value": dump_expression(value),
+                    }))
+                    .collect::<Vec<_>>(),
+            })
+        }
+        ExpressionAST::Literal(literal_ast) => {
+            let (literal_type, token, value) = match literal_ast {
+                LiteralAST::Bool(token) => ("bool", token, json!(token.token_content == "true")),
+                LiteralAST::Integer(token) => (
+                    "integer",
+                    token,
+                    token
+                        .token_content
+                        .parse::<i64>()
+                        .map_or(Value::Null, |integer| json!(integer)),
+                ),
+                LiteralAST::String(token) => ("string", token, json!(token.token_content)),
+            };
+
+            json!({
+                "kind": "literal",
+                "type": literal_type,
+                "value": value,
+                "token": dump_token(token),
+            })
+        }
+        ExpressionAST::Variable(token) => json!({
+            "kind": "variable",
+            "name": dump_token(token),
+        }),
+        ExpressionAST::Member(member_ast) => json!({
+            "kind": "member",
+            "module": dump_token(&member_ast.module),
+            "name": dump_token(&member_ast.name),
+        }),
+        ExpressionAST::Call(call_ast) => dump_call(call_ast),
+    }
+}
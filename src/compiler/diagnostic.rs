@@ -0,0 +1,86 @@
+use super::lexer::{Token, TokenType};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub file_path: String,
+    pub line_number: usize,
+    pub line_offset: usize,
+    pub length: usize,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(token: &Token, message: &str) -> Diagnostic {
+        let first_line = token.token_content.split('\n').next().unwrap_or("");
+        let mut length = first_line.trim_end().chars().count();
+
+        if token.token_type == TokenType::LiteralString {
+            length += 2;
+        }
+
+        Diagnostic {
+            file_path: token.file_path.clone(),
+            line_number: token.line_number,
+            line_offset: token.line_offset,
+            length: length.max(1),
+            message: message.to_owned(),
+        }
+    }
+
+    pub fn render(&self, src_content: &[String]) -> String {
+        let mut rendered = String::new();
+        let width = (self.line_number + 1).to_string().len();
+
+        writeln!(
+            rendered,
+            "{}:{}:{}",
+            self.file_path, self.line_number, self.line_offset
+        )
+        .unwrap();
+
+        if 2 <= self.line_number && self.line_number - 2 < src_content.len() {
+            writeln!(
+                rendered,
+                "{:>width$} | {}",
+                self.line_number - 1,
+                src_content[self.line_number - 2],
+                width = width
+            )
+            .unwrap();
+        }
+
+        writeln!(
+            rendered,
+            "{:>width$} | {}",
+            self.line_number,
+            src_content
+                .get(self.line_number - 1)
+                .map(|line| line.as_str())
+                .unwrap_or(""),
+            width = width
+        )
+        .unwrap();
+        writeln!(
+            rendered,
+            "{}{} {}",
+            " ".repeat(width + 3 + self.line_offset - 1),
+            "^".repeat(self.length),
+            self.message
+        )
+        .unwrap();
+
+        if self.line_number < src_content.len() {
+            writeln!(
+                rendered,
+                "{:>width$} | {}",
+                self.line_number + 1,
+                src_content[self.line_number],
+                width = width
+            )
+            .unwrap();
+        }
+
+        rendered
+    }
+}
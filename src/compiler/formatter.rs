@@ -0,0 +1,819 @@
+use super::diagnostic::Diagnostic;
+use super::lexer::{Lexer as RawLexer, Token, TokenType};
+use super::lookahead_lexer::LookaheadLexer as Lexer;
+use super::parser::{
+    parse, CallAST, ExpressionAST, IfAST, LiteralAST, ModifierAST, PipelineAST, AST,
+};
+use std::collections::HashMap;
+
+const INDENT: &str = "    ";
+const MAX_WIDTH: usize = 100;
+
+// The formatter walks the AST and consumes the raw token stream in the same order, so that
+// comments (which the parser discards) are emitted right where they were written.
+struct Formatter {
+    raw_token_vec: Vec<Token>,
+    index_map: HashMap<(usize, usize), usize>,
+    cursor: usize,
+    last_line: usize,
+    line_vec: Vec<String>,
+    current: String,
+    current_indent: usize,
+    indent: usize,
+    // Whether a comment broke the current statement, whose rest is indented once more.
+    continued: bool,
+}
+
+pub fn format(file_path: &str, content: &str) -> Result<String, Vec<Diagnostic>> {
+    let ast_vec = parse(&mut Lexer::new(file_path.to_owned(), content.to_owned()))?;
+
+    let mut raw_lexer = RawLexer::new(file_path.to_owned(), content.to_owned());
+    let mut raw_token_vec = Vec::new();
+
+    loop {
+        match raw_lexer.next() {
+            Ok(token) => {
+                let is_eof = token.token_type == TokenType::Eof;
+                raw_token_vec.push(token);
+
+                if is_eof {
+                    break;
+                }
+            }
+            Err(..) => unreachable!(),
+        }
+    }
+
+    let index_map = raw_token_vec
+        .iter()
+        .enumerate()
+        .filter(|(_, token)| token.token_type != TokenType::Comment)
+        .map(|(index, token)| ((token.line_number, token.line_offset), index))
+        .collect();
+
+    let mut formatter = Formatter {
+        raw_token_vec,
+        index_map,
+        cursor: 0,
+        last_line: 0,
+        line_vec: Vec::new(),
+        current: String::new(),
+        current_indent: 0,
+        indent: 0,
+        continued: false,
+    };
+
+    formatter.statement_vec(&ast_vec);
+    formatter.take_type(TokenType::Eof, false);
+
+    if !formatter.current.is_empty() {
+        formatter.newline();
+    }
+
+    if formatter.line_vec.is_empty() {
+        return Ok(String::new());
+    }
+
+    let mut formatted = formatter.line_vec.join("\n");
+    formatted.push('\n');
+    Ok(formatted)
+}
+
+impl Formatter {
+    fn write(&mut self, content: &str) {
+        if self.current.is_empty() {
+            self.current_indent = self.indent;
+        }
+
+        self.current.push_str(content);
+    }
+
+    fn newline(&mut self) {
+        self.line_vec.push(format!(
+            "{}{}",
+            INDENT.repeat(self.current_indent),
+            self.current
+        ));
+        self.current.clear();
+    }
+
+    fn blank_line(&mut self) {
+        match self.line_vec.last() {
+            Some(line) if !line.is_empty() && !line.ends_with('{') => {
+                self.line_vec.push(String::new());
+            }
+            _ => {}
+        }
+    }
+
+    fn width(&self) -> usize {
+        if self.current.is_empty() {
+            INDENT.len() * self.indent
+        } else {
+            INDENT.len() * self.current_indent + self.current.chars().count()
+        }
+    }
+
+    fn fits(&self, content: &str) -> bool {
+        self.width() + content.chars().count() <= MAX_WIDTH
+    }
+
+    fn take(&mut self, token: &Token, separate: bool) {
+        let index = self.index_map[&(token.line_number, token.line_offset)];
+        self.consume_until(index, separate);
+    }
+
+    fn take_type(&mut self, token_type: TokenType, separate: bool) -> usize {
+        let index = self.find_type(token_type);
+        self.consume_until(index, separate);
+        index
+    }
+
+    fn find_type(&self, token_type: TokenType) -> usize {
+        (self.cursor..self.raw_token_vec.len())
+            .find(|&index| self.raw_token_vec[index].token_type == token_type)
+            .unwrap_or(self.raw_token_vec.len() - 1)
+    }
+
+    fn next_type(&self) -> &TokenType {
+        &self.raw_token_vec[(self.cursor..self.raw_token_vec.len())
+            .find(|&index| self.raw_token_vec[index].token_type != TokenType::Comment)
+            .unwrap_or(self.raw_token_vec.len() - 1)]
+        .token_type
+    }
+
+    // Emits every comment before the given raw token and marks it as consumed. A statement
+    // separated from the previous one by blank lines keeps a single blank line.
+    fn consume_until(&mut self, index: usize, separate: bool) {
+        for comment_index in self.cursor..index {
+            if self.raw_token_vec[comment_index].token_type == TokenType::Comment {
+                self.comment(comment_index);
+            }
+        }
+
+        if index < self.cursor {
+            return;
+        }
+
+        if separate && self.last_line + 1 < self.raw_token_vec[index].line_number {
+            self.blank_line();
+        }
+
+        self.last_line = self.raw_token_vec[index].line_number;
+        self.cursor = index + 1;
+    }
+
+    fn comment(&mut self, index: usize) {
+        let line_number = self.raw_token_vec[index].line_number;
+        let content = self.raw_token_vec[index]
+            .token_content
+            .trim_end()
+            .to_owned();
+        let is_trailing = 0 < index && self.raw_token_vec[index - 1].line_number == line_number;
+
+        if is_trailing {
+            if !self.current.is_empty() {
+                let length = self.current.trim_end().len();
+                self.current.truncate(length);
+                self.write(" ");
+                self.write(&content);
+                self.newline();
+
+                if !self.continued {
+                    self.continued = true;
+                    self.indent += 1;
+                }
+            } else if let Some(line) = self.line_vec.last_mut() {
+                line.push(' ');
+                line.push_str(&content);
+            } else {
+                self.write(&content);
+                self.newline();
+            }
+        } else {
+            if !self.current.is_empty() {
+                self.newline();
+            }
+
+            if self.last_line + 1 < line_number {
+                self.blank_line();
+            }
+
+            self.write(&content);
+            self.newline();
+        }
+
+        self.last_line = line_number;
+    }
+
+    fn matching_index(&self, open_index: usize) -> usize {
+        let open_type = &self.raw_token_vec[open_index].token_type;
+        let close_type = match open_type {
+            TokenType::ParenL => TokenType::ParenR,
+            TokenType::BraceL => TokenType::BraceR,
+            TokenType::BracketL => TokenType::BracketR,
+            _ => unreachable!(),
+        };
+        let mut depth = 0;
+
+        for index in open_index..self.raw_token_vec.len() {
+            let token_type = &self.raw_token_vec[index].token_type;
+
+            if token_type == open_type {
+                depth += 1;
+            } else if token_type == &close_type {
+                depth -= 1;
+
+                if depth == 0 {
+                    return index;
+                }
+            }
+        }
+
+        self.raw_token_vec.len() - 1
+    }
+
+    fn has_comment(&self, begin_index: usize, end_index: usize) -> bool {
+        self.raw_token_vec[begin_index..end_index]
+            .iter()
+            .any(|token| token.token_type == TokenType::Comment)
+    }
+
+    // An array or dictionary the author broke after its opening bracket keeps one item per line.
+    fn is_broken(&self, open_index: usize) -> bool {
+        let open_type = &self.raw_token_vec[open_index].token_type;
+
+        (*open_type == TokenType::BracketL || *open_type == TokenType::BraceL)
+            && self.raw_token_vec[open_index].line_number
+                < self.raw_token_vec[open_index + 1].line_number
+    }
+
+    fn has_broken_literal(&self, begin_index: usize, end_index: usize) -> bool {
+        (begin_index..end_index).any(|index| self.is_broken(index))
+    }
+
+    fn statement_vec(&mut self, ast_vec: &[AST]) {
+        for ast in ast_vec.iter() {
+            self.statement(ast);
+        }
+    }
+
+    fn statement(&mut self, ast: &AST) {
+        let (indent, continued) = (self.indent, self.continued);
+        self.continued = false;
+        self.statement_body(ast);
+        self.indent = indent;
+        self.continued = continued;
+    }
+
+    fn statement_body(&mut self, ast: &AST) {
+        match ast {
+            AST::Import(import_ast) => {
+                self.keyword(TokenType::KeywordImport, "@import ");
+                self.expression(&import_ast.path);
+                self.take_type(TokenType::KeywordAs, false);
+                self.take(&import_ast.name, false);
+                self.write(&format!(" as {}", import_ast.name.token_content));
+                self.semicolon();
+            }
+            AST::Param(param_ast) => {
+                self.keyword(TokenType::KeywordParam, "@param ");
+                self.take(&param_ast.name, false);
+                self.take(&param_ast.value_type, false);
+                self.write(&format!(
+                    "{}: {}",
+                    param_ast.name.token_content, param_ast.value_type.token_content
+                ));
+
+                if let Some(default) = &param_ast.default {
+                    self.write(" = ");
+                    self.expression(default);
+                }
+
+                self.semicolon();
+            }
+            AST::Set(set_ast) => {
+                self.keyword(TokenType::KeywordSet, "@set ");
+                self.take(&set_ast.name, false);
+                self.write(&format!("{} = ", set_ast.name.token_content));
+                self.expression(&set_ast.value);
+                self.semicolon();
+            }
+            AST::Export(export_ast) => {
+                self.keyword(TokenType::KeywordExport, "@export ");
+                self.take(&export_ast.name, false);
+                self.write(&format!("{} = ", export_ast.name.token_content));
+                self.expression(&export_ast.value);
+                self.semicolon();
+            }
+            AST::Print(print_ast) => {
+                self.keyword(TokenType::KeywordPrint, "@print");
+                self.expression_list(&print_ast.expression_vec);
+                self.semicolon();
+            }
+            AST::PrintErr(print_err_ast) => {
+                self.keyword(TokenType::KeywordPrintErr, "@printErr");
+                self.expression_list(&print_err_ast.expression_vec);
+                self.semicolon();
+            }
+            AST::Return(return_ast) => {
+                self.keyword(TokenType::KeywordReturn, "@return");
+
+                if let Some(value) = &return_ast.value {
+                    self.write(" ");
+                    self.expression(value);
+                }
+
+                self.semicolon();
+            }
+            AST::Await(await_ast) => {
+                self.keyword(TokenType::KeywordAwait, "@await");
+
+                if let Some(name) = &await_ast.name {
+                    self.take(name, false);
+                    self.write(&format!(" {}", quote(&name.token_content)));
+                }
+
+                self.semicolon();
+            }
+            AST::AwaitAll => {
+                self.keyword(TokenType::KeywordAwait, "@await");
+                self.take_type(TokenType::KeywordAll, false);
+                self.write(" all");
+                self.semicolon();
+            }
+            AST::NonBlock(non_block_ast) => {
+                self.keyword(TokenType::KeywordNonBlock, "@nonblock ");
+
+                if let Some(name) = &non_block_ast.name {
+                    self.take(name, false);
+                    self.write(&format!("{} ", quote(&name.token_content)));
+                }
+
+                self.pipeline_modifiers(&non_block_ast.pipeline, false);
+                self.pipeline(&non_block_ast.pipeline);
+                self.newline();
+            }
+            AST::For(for_ast) => {
+                self.keyword(TokenType::KeywordFor, "@for ");
+                self.take(&for_ast.variable_name, false);
+                self.take_type(TokenType::KeywordIn, false);
+                self.write(&format!("{} in ", for_ast.variable_name.token_content));
+                self.expression(&for_ast.variable_in);
+                self.write(" ");
+                self.block(&for_ast.body_ast_vec);
+                self.newline();
+            }
+            AST::Break(..) => {
+                self.keyword(TokenType::KeywordBreak, "@break");
+                self.semicolon();
+            }
+            AST::Continue(..) => {
+                self.keyword(TokenType::KeywordContinue, "@continue");
+                self.semicolon();
+            }
+            AST::If(if_ast) => {
+                self.take_type(TokenType::At, true);
+                self.write("@");
+                self.if_statement(if_ast);
+                self.newline();
+            }
+            AST::Assert(assert_ast) => {
+                self.keyword(TokenType::KeywordAssert, "@assert ");
+                self.expression(&assert_ast.criteria);
+
+                if let Some(message) = &assert_ast.message {
+                    self.take_type(TokenType::Comma, false);
+                    self.write(", ");
+                    self.expression(message);
+                }
+
+                self.semicolon();
+            }
+            AST::Test(test_ast) => {
+                self.keyword(TokenType::KeywordTest, "@test ");
+                self.take(&test_ast.name, false);
+                self.write(&format!("{} ", quote(&test_ast.name.token_content)));
+                self.block(&test_ast.body_ast_vec);
+                self.newline();
+            }
+            AST::Pipeline(pipeline_ast) => {
+                if !self.pipeline_modifiers(pipeline_ast, true) {
+                    self.take(&pipeline_ast.name, true);
+                }
+
+                self.pipeline(pipeline_ast);
+                self.newline();
+            }
+            AST::Call(call_ast) => {
+                self.take(&call_ast.name, true);
+                self.call(call_ast);
+                self.semicolon();
+            }
+        }
+    }
+
+    fn keyword(&mut self, token_type: TokenType, content: &str) {
+        self.take_type(TokenType::At, true);
+        self.take_type(token_type, false);
+        self.write(content);
+    }
+
+    fn semicolon(&mut self) {
+        self.take_type(TokenType::Semicolon, false);
+        self.write(";");
+        self.newline();
+    }
+
+    // Emits the @result as, @retry and @timeout modifiers of a pipeline invocation in the order
+    // they are written, and returns whether there are any.
+    fn pipeline_modifiers(&mut self, pipeline_ast: &PipelineAST, separate: bool) -> bool {
+        let mut modifier_vec: Vec<(&Token, Option<&ModifierAST>)> = Vec::new();
+
+        if let Some(result_as) = &pipeline_ast.result_as {
+            modifier_vec.push((result_as, None));
+        }
+
+        for modifier_ast in pipeline_ast.retry.iter().chain(pipeline_ast.timeout.iter()) {
+            modifier_vec.push((&modifier_ast.keyword, Some(modifier_ast)));
+        }
+
+        modifier_vec.sort_by_key(|(token, _)| (token.line_number, token.line_offset));
+
+        for (index, (token, modifier_ast)) in modifier_vec.iter().enumerate() {
+            self.take_type(TokenType::At, separate && index == 0);
+
+            match modifier_ast {
+                Some(modifier_ast) => {
+                    self.take(&modifier_ast.keyword, false);
+                    self.write(&format!("@{} ", modifier_ast.keyword.token_content));
+                    self.expression(&modifier_ast.value);
+                    self.write(" ");
+                }
+                None => self.result_as(token),
+            }
+        }
+
+        !modifier_vec.is_empty()
+    }
+
+    fn result_as(&mut self, result_as: &Token) {
+        self.take_type(TokenType::KeywordResult, false);
+        self.take_type(TokenType::KeywordAs, false);
+        self.take(result_as, false);
+        self.write(&format!("@result as {} ", result_as.token_content));
+    }
+
+    fn if_statement(&mut self, if_ast: &IfAST) {
+        self.take_type(TokenType::KeywordIf, false);
+        self.write("if ");
+        self.expression(&if_ast.criteria);
+        self.write(" ");
+        self.block(&if_ast.if_ast_vec);
+
+        if let Some(else_ast_vec) = &if_ast.else_ast_vec {
+            self.take_type(TokenType::At, false);
+            self.take_type(TokenType::KeywordElse, false);
+            self.write(if self.current.is_empty() {
+                "@else "
+            } else {
+                " @else "
+            });
+
+            match (self.next_type(), else_ast_vec.first()) {
+                (TokenType::KeywordIf, Some(AST::If(else_if_ast))) => {
+                    self.if_statement(else_if_ast);
+                }
+                _ => self.block(else_ast_vec),
+            }
+        }
+    }
+
+    fn block(&mut self, ast_vec: &[AST]) {
+        let open_index = self.take_type(TokenType::BraceL, false);
+        let close_index = self.matching_index(open_index);
+
+        if ast_vec.is_empty() && !self.has_comment(open_index, close_index) {
+            self.consume_until(close_index, false);
+            self.write("{}");
+            return;
+        }
+
+        self.write("{");
+        self.newline();
+        self.indent += 1;
+        self.statement_vec(ast_vec);
+        self.take_type(TokenType::BraceR, false);
+        self.indent -= 1;
+        self.write("}");
+    }
+
+    fn pipeline(&mut self, pipeline_ast: &PipelineAST) {
+        self.take(&pipeline_ast.name, false);
+        self.write(&pipeline_ast.name.token_content);
+
+        let name_index =
+            self.index_map[&(pipeline_ast.name.line_number, pipeline_ast.name.line_offset)];
+        let semicolon_index = self.statement_end_index(name_index);
+        let flat = pipeline_ast
+            .argument_vec
+            .iter()
+            .map(|(name, value)| format!(" {}={}", name.token_content, flat_expression(value)))
+            .collect::<String>();
+
+        if !self.has_comment(name_index, semicolon_index)
+            && !self.has_broken_literal(name_index, semicolon_index)
+            && self.fits(&format!("{};", flat))
+        {
+            self.consume_until(semicolon_index, false);
+            self.write(&flat);
+            self.write(";");
+            return;
+        }
+
+        self.indent += 1;
+
+        for (name, value) in pipeline_ast.argument_vec.iter() {
+            self.newline();
+            self.take(name, false);
+            self.take_type(TokenType::Equal, false);
+            self.write(&format!("{}=", name.token_content));
+            self.expression(value);
+        }
+
+        self.take_type(TokenType::Semicolon, false);
+        self.write(";");
+        self.indent -= 1;
+    }
+
+    fn statement_end_index(&self, begin_index: usize) -> usize {
+        let mut depth = 0;
+
+        for index in begin_index..self.raw_token_vec.len() {
+            match self.raw_token_vec[index].token_type {
+                TokenType::ParenL | TokenType::BraceL | TokenType::BracketL => depth += 1,
+                TokenType::ParenR | TokenType::BraceR | TokenType::BracketR => depth -= 1,
+                TokenType::Semicolon if depth == 0 => return index,
+                _ => {}
+            }
+        }
+
+        self.raw_token_vec.len() - 1
+    }
+
+    fn expression_list(&mut self, expression_vec: &[ExpressionAST]) {
+        for expression_ast in expression_vec.iter() {
+            self.write(" ");
+            self.expression(expression_ast);
+        }
+    }
+
+    fn expression(&mut self, expression_ast: &ExpressionAST) {
+        match expression_ast {
+            ExpressionAST::Array(array) => {
+                let item_vec = array.iter().map(Item::Expression).collect::<Vec<_>>();
+                self.sequence(TokenType::BracketL, "[", &item_vec, "]");
+            }
+            ExpressionAST::Dictionary(dictionary) => {
+                let mut entry_vec = dictionary.values().collect::<Vec<_>>();
+                entry_vec.sort_by_key(|(key, _)| (key.line_number, key.line_offset));
+
+                let item_vec = entry_vec
+                    .into_iter()
+                    .map(|(key, value)| Item::Entry(key, value))
+                    .collect::<Vec<_>>();
+                self.sequence(TokenType::BraceL, "{", &item_vec, "}");
+            }
+            ExpressionAST::Literal(literal_ast) => {
+                let token = match literal_ast {
+                    LiteralAST::Bool(token)
+                    | LiteralAST::Integer(token)
+                    | LiteralAST::String(token) => token,
+                };
+
+                self.take(token, false);
+                self.write(&flat_expression(expression_ast));
+            }
+            ExpressionAST::Variable(token) => {
+                self.take(token, false);
+                self.write(&token.token_content);
+            }
+            ExpressionAST::Member(member_ast) => {
+                self.take(&member_ast.module, false);
+                self.take_type(TokenType::Dot, false);
+                self.take(&member_ast.name, false);
+                self.write(&flat_expression(expression_ast));
+            }
+            ExpressionAST::Call(call_ast) => {
+                self.take(&call_ast.name, false);
+                self.call(call_ast);
+            }
+        }
+    }
+
+    fn call(&mut self, call_ast: &CallAST) {
+        let item_vec = call_ast
+            .argument_vec
+            .iter()
+            .map(Item::Expression)
+            .collect::<Vec<_>>();
+
+        self.write(&call_ast.name.token_content);
+        self.sequence(TokenType::ParenL, "(", &item_vec, ")");
+    }
+
+    // Emits a bracketed, comma separated sequence on a single line if it fits and contains no
+    // comments, one item per line otherwise.
+    fn sequence(&mut self, open_type: TokenType, open: &str, item_vec: &[Item], close: &str) {
+        let open_index = self.take_type(open_type, false);
+        let close_index = self.matching_index(open_index);
+        let flat = format!(
+            "{}{}{}",
+            open,
+            item_vec
+                .iter()
+                .map(|item| item.flat())
+                .collect::<Vec<_>>()
+                .join(", "),
+            close
+        );
+
+        if !self.has_comment(open_index, close_index)
+            && !self.has_broken_literal(open_index, close_index)
+            && self.fits(&flat)
+        {
+            self.consume_until(close_index, false);
+            self.write(&flat);
+            return;
+        }
+
+        self.write(open);
+        self.indent += 1;
+
+        for item in item_vec.iter() {
+            self.newline();
+
+            match item {
+                Item::Expression(expression_ast) => self.expression(expression_ast),
+                Item::Entry(key, value) => {
+                    self.take(key, false);
+                    self.take_type(TokenType::Colon, false);
+                    self.write(&format!("{}: ", flat_key(key)));
+                    self.expression(value);
+                }
+            }
+
+            self.write(",");
+        }
+
+        self.newline();
+        self.consume_until(close_index, false);
+        self.indent -= 1;
+        self.write(close);
+    }
+}
+
+enum Item<'ast> {
+    Expression(&'ast ExpressionAST),
+    Entry(&'ast Token, &'ast ExpressionAST),
+}
+
+impl<'ast> Item<'ast> {
+    fn flat(&self) -> String {
+        match self {
+            Item::Expression(expression_ast) => flat_expression(expression_ast),
+            Item::Entry(key, value) => format!("{}: {}", flat_key(key), flat_expression(value)),
+        }
+    }
+}
+
+fn flat_expression(expression_ast: &ExpressionAST) -> String {
+    match expression_ast {
+        ExpressionAST::Array(array) => format!(
+            "[{}]",
+            array
+                .iter()
+                .map(flat_expression)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        ExpressionAST::Dictionary(dictionary) => {
+            let mut entry_vec = dictionary.values().collect::<Vec<_>>();
+            entry_vec.sort_by_key(|(key, _)| (key.line_number, key.line_offset));
+
+            format!(
+                "{{{}}}",
+                entry_vec
+                    .into_iter()
+                    .map(|(key, value)| format!("{}: {}", flat_key(key), flat_expression(value)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        }
+        ExpressionAST::Literal(LiteralAST::String(token)) => quote(&token.token_content),
+        ExpressionAST::Literal(LiteralAST::Bool(token))
+        | ExpressionAST::Literal(LiteralAST::Integer(token))
+        | ExpressionAST::Variable(token) => token.token_content.clone(),
+        ExpressionAST::Member(member_ast) => format!(
+            "{}.{}",
+            member_ast.module.token_content, member_ast.name.token_content
+        ),
+        ExpressionAST::Call(call_ast) => format!(
+            "{}({})",
+            call_ast.name.token_content,
+            call_ast
+                .argument_vec
+                .iter()
+                .map(flat_expression)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+fn flat_key(key: &Token) -> String {
+    if key.token_type == TokenType::LiteralString {
+        quote(&key.token_content)
+    } else {
+        key.token_content.clone()
+    }
+}
+
+fn quote(content: &str) -> String {
+    let mut quoted = String::with_capacity(content.len() + 2);
+
+    quoted.push('"');
+
+    for character in content.chars() {
+        match character {
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            '\\' => quoted.push_str("\\\\"),
+            '\0' => quoted.push_str("\\0"),
+            '"' => quoted.push_str("\\\""),
+            _ => quoted.push(character),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format_ok(content: &str) -> String {
+        match format("test.piped", content) {
+            Ok(formatted) => formatted,
+            Err(..) => panic!("unexpected diagnostics"),
+        }
+    }
+
+    #[test]
+    fn formats_the_examples_idempotently() {
+        for content in [
+            include_str!("../../examples/basics.piped"),
+            include_str!("../../examples/return_a_value.piped"),
+            include_str!("../../examples/subpipeline.piped"),
+            include_str!("../../examples/test.piped"),
+        ] {
+            let formatted = format_ok(content);
+
+            assert_eq!(format_ok(&formatted), formatted);
+        }
+    }
+
+    #[test]
+    fn keeps_line_breaks_of_dictionaries_and_arrays() {
+        let content = "exec cmd=\"ls\" envs={\n\"a\": \"1\",\n} params=[\"-l\"];\n";
+
+        assert_eq!(
+            format_ok(content),
+            "exec\n    cmd=\"ls\"\n    envs={\n        \"a\": \"1\",\n    }\n    params=[\"-l\"];\n"
+        );
+    }
+
+    #[test]
+    fn indents_statements_continued_after_a_comment() {
+        let content = "@set x =  // c\n1;\n@nonblock // r\nexec cmd=\"true\";\n@print x;\n";
+        let formatted = format_ok(content);
+
+        assert_eq!(
+            formatted,
+            "@set x = // c\n    1;\n@nonblock // r\n    exec cmd=\"true\";\n@print x;\n"
+        );
+        assert_eq!(format_ok(&formatted), formatted);
+    }
+
+    #[test]
+    fn joins_short_literals_written_on_one_line() {
+        let content = "exec cmd=\"ls\"\n    params=[\"-l\", \"-a\"];\n";
+
+        assert_eq!(
+            format_ok(content),
+            "exec cmd=\"ls\" params=[\"-l\", \"-a\"];\n"
+        );
+    }
+}
@@ -5,13 +5,14 @@ enum AdvanceMode {
     NoAdvance,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub enum TokenType {
     Unknown,
     Eof,
     Id,
     At,              // @
     Comma,           // ,
+    Dot,             // .
     Colon,           // :
     Semicolon,       // ;
     Equal,           // =
@@ -40,10 +41,14 @@ pub enum TokenType {
     KeywordContinue, // continue
     KeywordIf,       // if
     KeywordElse,     // else
+    KeywordParam,    // param
+    KeywordAssert,   // assert
+    KeywordTest,     // test
+    KeywordExport,   // export
     Comment,         // // ...
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Token {
     pub file_path: String,
     pub token_type: TokenType,
@@ -60,6 +65,8 @@ pub struct Lexer {
     pub index: usize,
     pub line_offset: usize,
     pub line_number: usize,
+    // Whether the last token other than a comment was an @.
+    after_at: bool,
 }
 
 pub enum LexerError {
@@ -85,6 +92,7 @@ impl Lexer {
             index: 0,
             line_offset: 1,
             line_number: 1,
+            after_at: false,
         }
     }
 
@@ -200,6 +208,28 @@ impl Lexer {
     }
 
     pub fn next(&mut self) -> Result<Token, LexerError> {
+        let mut token = self.next_token()?;
+
+        // Keywords added after the language was in use are keywords only right after an @, so that
+        // scripts using them as names keep working.
+        if self.after_at && token.token_type == TokenType::Id {
+            token.token_type = match token.token_content.as_ref() {
+                "param" => TokenType::KeywordParam,
+                "assert" => TokenType::KeywordAssert,
+                "test" => TokenType::KeywordTest,
+                "export" => TokenType::KeywordExport,
+                _ => TokenType::Id,
+            };
+        }
+
+        if token.token_type != TokenType::Comment {
+            self.after_at = token.token_type == TokenType::At;
+        }
+
+        Ok(token)
+    }
+
+    fn next_token(&mut self) -> Result<Token, LexerError> {
         let blackspace = self.pick_blackspace();
 
         let mut token = Token {
@@ -231,6 +261,10 @@ impl Lexer {
                 self.next_character(AdvanceMode::Pre);
                 return return_token(TokenType::Comma, blackspace.to_string());
             }
+            '.' => {
+                self.next_character(AdvanceMode::Pre);
+                return return_token(TokenType::Dot, blackspace.to_string());
+            }
             ':' => {
                 self.next_character(AdvanceMode::Pre);
                 return return_token(TokenType::Colon, blackspace.to_string());
@@ -337,7 +371,7 @@ impl Lexer {
                     }
                 }
 
-                if self.ch() != '"' {
+                if self.is_eof() || self.ch() != '"' {
                     return Err(LexerError::StringNotClosed {
                         0: return_token(TokenType::Unknown, string).ok().unwrap(),
                     });
@@ -392,3 +426,68 @@ impl Lexer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_types(content: &str) -> Vec<TokenType> {
+        let mut lexer = Lexer::new("test.piped".to_owned(), content.to_owned());
+        let mut token_type_vec = Vec::new();
+
+        loop {
+            match lexer.next() {
+                Ok(token) if token.token_type == TokenType::Eof => return token_type_vec,
+                Ok(token) => token_type_vec.push(token.token_type),
+                Err(..) => panic!("unexpected lexer error"),
+            }
+        }
+    }
+
+    #[test]
+    fn contextual_keywords_are_keywords_only_after_at() {
+        assert_eq!(
+            token_types("@param"),
+            vec![TokenType::At, TokenType::KeywordParam]
+        );
+        assert_eq!(
+            token_types("@assert test;"),
+            vec![
+                TokenType::At,
+                TokenType::KeywordAssert,
+                TokenType::Id,
+                TokenType::Semicolon,
+            ]
+        );
+        assert_eq!(
+            token_types("@export export = 1;"),
+            vec![
+                TokenType::At,
+                TokenType::KeywordExport,
+                TokenType::Id,
+                TokenType::Equal,
+                TokenType::LiteralInteger,
+                TokenType::Semicolon,
+            ]
+        );
+        assert_eq!(
+            token_types("@set param = 1;"),
+            vec![
+                TokenType::At,
+                TokenType::KeywordSet,
+                TokenType::Id,
+                TokenType::Equal,
+                TokenType::LiteralInteger,
+                TokenType::Semicolon,
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_do_not_end_the_keyword_position() {
+        assert_eq!(
+            token_types("@ // comment\nparam"),
+            vec![TokenType::At, TokenType::Comment, TokenType::KeywordParam]
+        );
+    }
+}
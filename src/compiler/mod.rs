@@ -1,3 +1,6 @@
+pub mod ast_dump;
+pub mod diagnostic;
+pub mod formatter;
 pub mod lexer;
 pub mod lookahead_lexer;
 pub mod parser;
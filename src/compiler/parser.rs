@@ -1,13 +1,14 @@
+use super::diagnostic::Diagnostic;
 use super::lexer::{LexerError, Token, TokenType};
 use super::lookahead_lexer::LookaheadLexer as Lexer;
-use std::cmp::max;
 use std::collections::HashMap;
-use std::iter::repeat;
 
 #[derive(Debug)]
 pub enum AST {
     Import(ImportAST),
+    Param(ParamAST),
     Set(SetAST),
+    Export(SetAST),
     Print(PrintAST),
     PrintErr(PrintErrAST),
     Return(ReturnAST),
@@ -15,9 +16,11 @@ pub enum AST {
     AwaitAll,
     NonBlock(NonBlockAST),
     For(ForAST),
-    Break,
-    Continue,
+    Break(Token),
+    Continue(Token),
     If(IfAST),
+    Assert(AssertAST),
+    Test(TestAST),
     Pipeline(PipelineAST),
     Call(CallAST),
 }
@@ -28,6 +31,13 @@ pub struct ImportAST {
     pub path: ExpressionAST,
 }
 
+#[derive(Debug)]
+pub struct ParamAST {
+    pub name: Token,
+    pub value_type: Token,
+    pub default: Option<ExpressionAST>,
+}
+
 #[derive(Debug)]
 pub struct SetAST {
     pub name: Token,
@@ -74,19 +84,42 @@ pub struct IfAST {
     pub else_ast_vec: Option<Vec<AST>>,
 }
 
+#[derive(Debug)]
+pub struct AssertAST {
+    pub token: Token,
+    pub criteria: ExpressionAST,
+    pub message: Option<ExpressionAST>,
+}
+
+#[derive(Debug)]
+pub struct TestAST {
+    pub name: Token,
+    pub body_ast_vec: Vec<AST>,
+}
+
 #[derive(Debug)]
 pub struct PipelineAST {
     pub result_as: Option<Token>,
+    pub retry: Option<ModifierAST>,
+    pub timeout: Option<ModifierAST>,
     pub name: Token,
     pub argument_vec: Vec<(Token, ExpressionAST)>,
 }
 
+// A '@retry' or '@timeout' modifier of a pipeline invocation.
+#[derive(Debug)]
+pub struct ModifierAST {
+    pub keyword: Token,
+    pub value: ExpressionAST,
+}
+
 #[derive(Debug)]
 pub enum ExpressionAST {
     Array(Vec<ExpressionAST>),
     Dictionary(HashMap<String, (Token, ExpressionAST)>),
     Literal(LiteralAST),
     Variable(Token),
+    Member(MemberAST),
     Call(CallAST),
 }
 
@@ -97,90 +130,175 @@ pub enum LiteralAST {
     String(Token),
 }
 
+// A variable exported by an imported pipeline, e.g. `lib.name`.
+#[derive(Debug)]
+pub struct MemberAST {
+    pub module: Token,
+    pub name: Token,
+}
+
 #[derive(Debug)]
 pub struct CallAST {
     pub name: Token,
     pub argument_vec: Vec<ExpressionAST>,
 }
 
+const PARAM_TYPE_NAMES: [&str; 5] = ["Array", "Dictionary", "Bool", "Integer", "String"];
+// Modifiers are not keywords, so that pipelines can still take arguments named after them.
+const MODIFIER_NAMES: [&str; 2] = ["retry", "timeout"];
+
 enum ParserStatus {
     TopLevel,
     Statement,
     StatementImport,
+    StatementParam,
     StatementSet,
+    StatementExport,
     StatementPrint,
     StatementPrintErr,
     StatementAwait,
     StatementReturn,
-    StatementResult,
+    StatementModifier(Token),
     StatementNonBlock,
     StatementFor,
-    StatementBreak,
-    StatementContinue,
+    StatementBreak(Token),
+    StatementContinue(Token),
     StatementIf,
     StatementIfNext(IfAST),
     StatementIfNextStatement(IfAST),
     StatementIfNextElse(IfAST),
+    StatementAssert(Token),
+    StatementTest,
 }
 
-pub fn parse(lexer: &mut Lexer) -> Result<Vec<AST>, ()> {
-    let ast_vec = parse_statement_vec(lexer)?;
-    next_token(lexer, TokenType::Eof)?;
-    Ok(ast_vec)
+struct Parser<'lexer> {
+    lexer: &'lexer mut Lexer,
+    diagnostic_vec: Vec<Diagnostic>,
+    // Set once an error is reported and cleared once the parser has synchronized again; errors in
+    // between are mostly caused by the first one, so they are not reported.
+    recovering: bool,
 }
 
-fn parse_statement_vec(lexer: &mut Lexer) -> Result<Vec<AST>, ()> {
+pub fn parse(lexer: &mut Lexer) -> Result<Vec<AST>, Vec<Diagnostic>> {
+    let mut parser = Parser {
+        lexer,
+        diagnostic_vec: Vec::new(),
+        recovering: false,
+    };
     let mut ast_vec = Vec::new();
 
     loop {
-        let statement_vec = parse_statement(lexer, ParserStatus::TopLevel)?;
+        ast_vec.extend(parse_statement_vec(&mut parser));
 
-        if statement_vec.is_empty() {
-            break;
+        match next(&mut parser) {
+            Ok(token) if token.token_type == TokenType::Eof => break,
+            Ok(token) => {
+                report(
+                    &mut parser,
+                    &token,
+                    "A statement or a pipeline invocation only can be placed here.",
+                );
+                // The stray token is skipped, so what follows starts afresh.
+                parser.recovering = false;
+            }
+            Err(()) => {}
         }
+    }
+
+    if parser.diagnostic_vec.is_empty() {
+        Ok(ast_vec)
+    } else {
+        Err(parser.diagnostic_vec)
+    }
+}
 
-        ast_vec.extend(statement_vec);
+// Parses an input that consists of a single expression only, e.g. a line entered in the REPL.
+pub fn parse_expression_input(lexer: &mut Lexer) -> Result<ExpressionAST, Vec<Diagnostic>> {
+    let mut parser = Parser {
+        lexer,
+        diagnostic_vec: Vec::new(),
+        recovering: false,
+    };
+
+    if let Ok(expression_ast) = parse_expression(&mut parser) {
+        match next(&mut parser) {
+            Ok(token) if token.token_type == TokenType::Eof => return Ok(expression_ast),
+            Ok(token) => report(
+                &mut parser,
+                &token,
+                "Nothing can be placed after an expression.",
+            ),
+            Err(()) => {}
+        }
     }
 
-    Ok(ast_vec)
+    Err(parser.diagnostic_vec)
 }
 
-fn parse_statement(lexer: &mut Lexer, status: ParserStatus) -> Result<Vec<AST>, ()> {
+fn parse_statement_vec(parser: &mut Parser) -> Vec<AST> {
+    let mut ast_vec = Vec::new();
+
+    loop {
+        match parse_statement(parser, ParserStatus::TopLevel) {
+            Ok(statement_vec) => {
+                if statement_vec.is_empty() {
+                    break;
+                }
+
+                parser.recovering = false;
+                ast_vec.extend(statement_vec);
+            }
+            Err(()) => synchronize(parser),
+        }
+    }
+
+    ast_vec
+}
+
+fn parse_statement(parser: &mut Parser, status: ParserStatus) -> Result<Vec<AST>, ()> {
     let mut ast_vec = Vec::new();
     let mut status = status;
 
     'parse: loop {
         if let ParserStatus::TopLevel = status {
-            let token = next_lookahead(lexer)?;
+            let token = next_lookahead(parser)?;
 
             if token.token_type == TokenType::Eof {
                 return Ok(ast_vec);
             }
 
             if token.token_type == TokenType::At {
-                next(lexer)?;
+                next(parser)?;
                 status = ParserStatus::Statement;
                 continue 'parse;
             }
 
             if token.token_type == TokenType::Id {
-                ast_vec.push(parse_pipeline(lexer)?);
+                ast_vec.push(parse_pipeline(parser)?);
                 return Ok(ast_vec);
             }
 
             return Ok(ast_vec);
         } else if let ParserStatus::Statement = status {
-            let statement_token = next(lexer)?;
+            let statement_token = next(parser)?;
 
             match statement_token.token_type {
                 TokenType::KeywordImport => {
                     status = ParserStatus::StatementImport;
                     continue 'parse;
                 }
+                TokenType::KeywordParam => {
+                    status = ParserStatus::StatementParam;
+                    continue 'parse;
+                }
                 TokenType::KeywordSet => {
                     status = ParserStatus::StatementSet;
                     continue 'parse;
                 }
+                TokenType::KeywordExport => {
+                    status = ParserStatus::StatementExport;
+                    continue 'parse;
+                }
                 TokenType::KeywordPrint => {
                     status = ParserStatus::StatementPrint;
                     continue 'parse;
@@ -198,7 +316,11 @@ fn parse_statement(lexer: &mut Lexer, status: ParserStatus) -> Result<Vec<AST>,
                     continue 'parse;
                 }
                 TokenType::KeywordResult => {
-                    status = ParserStatus::StatementResult;
+                    status = ParserStatus::StatementModifier(statement_token);
+                    continue 'parse;
+                }
+                TokenType::Id if is_modifier(&statement_token) => {
+                    status = ParserStatus::StatementModifier(statement_token);
                     continue 'parse;
                 }
                 TokenType::KeywordNonBlock => {
@@ -210,34 +332,42 @@ fn parse_statement(lexer: &mut Lexer, status: ParserStatus) -> Result<Vec<AST>,
                     continue 'parse;
                 }
                 TokenType::KeywordBreak => {
-                    status = ParserStatus::StatementBreak;
+                    status = ParserStatus::StatementBreak(statement_token);
                     continue 'parse;
                 }
                 TokenType::KeywordContinue => {
-                    status = ParserStatus::StatementContinue;
+                    status = ParserStatus::StatementContinue(statement_token);
                     continue 'parse;
                 }
                 TokenType::KeywordIf => {
                     status = ParserStatus::StatementIf;
                     continue 'parse;
                 }
+                TokenType::KeywordAssert => {
+                    status = ParserStatus::StatementAssert(statement_token);
+                    continue 'parse;
+                }
+                TokenType::KeywordTest => {
+                    status = ParserStatus::StatementTest;
+                    continue 'parse;
+                }
                 _ => {
-                    print_last_line_of_token(
-                        lexer,
+                    report(
+                        parser,
                         &statement_token,
-                        "A 'import', 'set', 'print', 'printErr', 'return', 'await', 'result', 'nonblock', 'for', 'break', 'continue' and 'if' keyword only can be used here.",
+                        "A 'import', 'param', 'set', 'export', 'print', 'printErr', 'return', 'await', 'result', 'retry', 'timeout', 'nonblock', 'for', 'break', 'continue', 'if', 'assert' and 'test' keyword only can be used here.",
                     );
                     return Err(());
                 }
             }
         } else if let ParserStatus::StatementImport = status {
-            let expression_ast = parse_expression(lexer)?;
+            let expression_ast = parse_expression(parser)?;
 
-            next_token(lexer, TokenType::KeywordAs)?;
+            next_token(parser, TokenType::KeywordAs)?;
 
-            let name_token = next_token(lexer, TokenType::Id)?;
+            let name_token = next_token(parser, TokenType::Id)?;
 
-            next_token(lexer, TokenType::Semicolon)?;
+            next_token(parser, TokenType::Semicolon)?;
 
             ast_vec.push(AST::Import {
                 0: ImportAST {
@@ -246,15 +376,47 @@ fn parse_statement(lexer: &mut Lexer, status: ParserStatus) -> Result<Vec<AST>,
                 },
             });
 
+            return Ok(ast_vec);
+        } else if let ParserStatus::StatementParam = status {
+            let name_token = next_token(parser, TokenType::Id)?;
+
+            next_token(parser, TokenType::Colon)?;
+
+            let value_type_token = next_token(parser, TokenType::Id)?;
+
+            if !PARAM_TYPE_NAMES.contains(&value_type_token.token_content.as_str()) {
+                report(
+                    parser,
+                    &value_type_token,
+                    "A 'Array', 'Dictionary', 'Bool', 'Integer' and 'String' type only can be used here.",
+                );
+                return Err(());
+            }
+
+            let default = if next_lookahead(parser)?.token_type == TokenType::Equal {
+                next(parser)?;
+                Some(parse_expression(parser)?)
+            } else {
+                None
+            };
+
+            next_token(parser, TokenType::Semicolon)?;
+
+            ast_vec.push(AST::Param(ParamAST {
+                name: name_token,
+                value_type: value_type_token,
+                default,
+            }));
+
             return Ok(ast_vec);
         } else if let ParserStatus::StatementSet = status {
-            let name_token = next_token(lexer, TokenType::Id)?;
+            let name_token = next_token(parser, TokenType::Id)?;
 
-            next_token(lexer, TokenType::Equal)?;
+            next_token(parser, TokenType::Equal)?;
 
-            let expression_ast = parse_expression(lexer)?;
+            let expression_ast = parse_expression(parser)?;
 
-            next_token(lexer, TokenType::Semicolon)?;
+            next_token(parser, TokenType::Semicolon)?;
 
             ast_vec.push(AST::Set {
                 0: SetAST {
@@ -263,15 +425,30 @@ fn parse_statement(lexer: &mut Lexer, status: ParserStatus) -> Result<Vec<AST>,
                 },
             });
 
+            return Ok(ast_vec);
+        } else if let ParserStatus::StatementExport = status {
+            let name_token = next_token(parser, TokenType::Id)?;
+
+            next_token(parser, TokenType::Equal)?;
+
+            let expression_ast = parse_expression(parser)?;
+
+            next_token(parser, TokenType::Semicolon)?;
+
+            ast_vec.push(AST::Export(SetAST {
+                name: name_token,
+                value: expression_ast,
+            }));
+
             return Ok(ast_vec);
         } else if let ParserStatus::StatementPrint = status {
             let mut expression_vec = Vec::new();
 
-            while next_lookahead(lexer)?.token_type != TokenType::Semicolon {
-                expression_vec.push(parse_expression(lexer)?);
+            while next_lookahead(parser)?.token_type != TokenType::Semicolon {
+                expression_vec.push(parse_expression(parser)?);
             }
 
-            next(lexer)?;
+            next(parser)?;
 
             ast_vec.push(AST::Print {
                 0: PrintAST { expression_vec },
@@ -281,11 +458,11 @@ fn parse_statement(lexer: &mut Lexer, status: ParserStatus) -> Result<Vec<AST>,
         } else if let ParserStatus::StatementPrintErr = status {
             let mut expression_vec = Vec::new();
 
-            while next_lookahead(lexer)?.token_type != TokenType::Semicolon {
-                expression_vec.push(parse_expression(lexer)?);
+            while next_lookahead(parser)?.token_type != TokenType::Semicolon {
+                expression_vec.push(parse_expression(parser)?);
             }
 
-            next(lexer)?;
+            next(parser)?;
 
             ast_vec.push(AST::PrintErr {
                 0: PrintErrAST { expression_vec },
@@ -294,25 +471,25 @@ fn parse_statement(lexer: &mut Lexer, status: ParserStatus) -> Result<Vec<AST>,
             return Ok(ast_vec);
         } else if let ParserStatus::StatementReturn = status {
             ast_vec.push(AST::Return(ReturnAST {
-                value: if next_lookahead(lexer)?.token_type == TokenType::Semicolon {
-                    next(lexer)?;
+                value: if next_lookahead(parser)?.token_type == TokenType::Semicolon {
+                    next(parser)?;
                     None
                 } else {
-                    let value = Some(parse_expression(lexer)?);
-                    next_token(lexer, TokenType::Semicolon)?;
+                    let value = Some(parse_expression(parser)?);
+                    next_token(parser, TokenType::Semicolon)?;
                     value
                 },
             }));
             return Ok(ast_vec);
         } else if let ParserStatus::StatementAwait = status {
-            let semicolon_or_string_or_all = next(lexer)?;
+            let semicolon_or_string_or_all = next(parser)?;
 
             ast_vec.push(match semicolon_or_string_or_all.token_type {
                 TokenType::Semicolon => AST::Await {
                     0: AwaitAST { name: None },
                 },
                 TokenType::LiteralString => {
-                    next_token(lexer, TokenType::Semicolon)?;
+                    next_token(parser, TokenType::Semicolon)?;
                     AST::Await {
                         0: AwaitAST {
                             name: Some(semicolon_or_string_or_all),
@@ -320,12 +497,12 @@ fn parse_statement(lexer: &mut Lexer, status: ParserStatus) -> Result<Vec<AST>,
                     }
                 }
                 TokenType::KeywordAll => {
-                    next_token(lexer, TokenType::Semicolon)?;
+                    next_token(parser, TokenType::Semicolon)?;
                     AST::AwaitAll
                 }
                 _ => {
-                    print_last_line_of_token(
-                        lexer,
+                    report(
+                        parser,
                         &semicolon_or_string_or_all,
                         "An await statement should be followed by a semicolon, a string literal or an 'all' keyword.",
                     );
@@ -334,30 +511,30 @@ fn parse_statement(lexer: &mut Lexer, status: ParserStatus) -> Result<Vec<AST>,
             });
 
             return Ok(ast_vec);
-        } else if let ParserStatus::StatementResult = status {
-            ast_vec.push(parse_pipeline_result(lexer)?);
+        } else if let ParserStatus::StatementModifier(modifier_token) = status {
+            ast_vec.push(parse_pipeline_modifier(parser, modifier_token)?);
             return Ok(ast_vec);
         } else if let ParserStatus::StatementNonBlock = status {
-            let name_token = next_lookahead(lexer)?;
+            let name_token = next_lookahead(parser)?;
 
             ast_vec.push(AST::NonBlock(NonBlockAST {
                 name: if name_token.token_type == TokenType::LiteralString {
-                    next(lexer)?;
+                    next(parser)?;
                     Some(name_token)
                 } else {
                     None
                 },
-                pipeline: match if next_lookahead(lexer)?.token_type == TokenType::At {
-                    next(lexer)?;
-                    next_token(lexer, TokenType::KeywordResult)?;
-                    parse_pipeline_result(lexer)
+                pipeline: match if next_lookahead(parser)?.token_type == TokenType::At {
+                    next(parser)?;
+                    let modifier_token = next(parser)?;
+                    parse_pipeline_modifier(parser, modifier_token)
                 } else {
-                    parse_pipeline(lexer)
+                    parse_pipeline(parser)
                 }? {
                     AST::Pipeline(pipeline_ast) => pipeline_ast,
                     AST::Call(call_ast) => {
-                        print_last_line_of_token(
-                            lexer,
+                        report(
+                            parser,
                             &call_ast.name,
                             "A non-block statement should be followed by a pipeline statement.",
                         );
@@ -369,10 +546,10 @@ fn parse_statement(lexer: &mut Lexer, status: ParserStatus) -> Result<Vec<AST>,
 
             return Ok(ast_vec);
         } else if let ParserStatus::StatementFor = status {
-            let variable_name_token = next_token(lexer, TokenType::Id)?;
-            next_token(lexer, TokenType::KeywordIn)?;
-            let variable_in_expression = parse_expression(lexer)?;
-            let body_ast_vec = parse_block(lexer)?;
+            let variable_name_token = next_token(parser, TokenType::Id)?;
+            next_token(parser, TokenType::KeywordIn)?;
+            let variable_in_expression = parse_expression(parser)?;
+            let body_ast_vec = parse_block(parser)?;
 
             ast_vec.push(AST::For(ForAST {
                 variable_name: variable_name_token,
@@ -381,22 +558,22 @@ fn parse_statement(lexer: &mut Lexer, status: ParserStatus) -> Result<Vec<AST>,
             }));
 
             return Ok(ast_vec);
-        } else if let ParserStatus::StatementBreak = status {
-            next_token(lexer, TokenType::Semicolon)?;
-            ast_vec.push(AST::Break);
+        } else if let ParserStatus::StatementBreak(break_token) = status {
+            next_token(parser, TokenType::Semicolon)?;
+            ast_vec.push(AST::Break(break_token));
             return Ok(ast_vec);
-        } else if let ParserStatus::StatementContinue = status {
-            next_token(lexer, TokenType::Semicolon)?;
-            ast_vec.push(AST::Continue);
+        } else if let ParserStatus::StatementContinue(continue_token) = status {
+            next_token(parser, TokenType::Semicolon)?;
+            ast_vec.push(AST::Continue(continue_token));
             return Ok(ast_vec);
         } else if let ParserStatus::StatementIf = status {
-            status = ParserStatus::StatementIfNext(parse_if(lexer)?);
+            status = ParserStatus::StatementIfNext(parse_if(parser)?);
             continue 'parse;
         } else if let ParserStatus::StatementIfNext(if_ast) = status {
-            let token = next_lookahead(lexer)?;
+            let token = next_lookahead(parser)?;
 
             if token.token_type == TokenType::At {
-                next(lexer)?;
+                next(parser)?;
                 status = ParserStatus::StatementIfNextStatement(if_ast);
                 continue 'parse;
             }
@@ -404,7 +581,7 @@ fn parse_statement(lexer: &mut Lexer, status: ParserStatus) -> Result<Vec<AST>,
             ast_vec.push(AST::If(if_ast));
             return Ok(ast_vec);
         } else if let ParserStatus::StatementIfNextStatement(if_ast) = status {
-            let statement_token = next(lexer)?;
+            let statement_token = next(parser)?;
 
             match statement_token.token_type {
                 TokenType::KeywordImport => {
@@ -412,11 +589,21 @@ fn parse_statement(lexer: &mut Lexer, status: ParserStatus) -> Result<Vec<AST>,
                     status = ParserStatus::StatementImport;
                     continue 'parse;
                 }
+                TokenType::KeywordParam => {
+                    ast_vec.push(AST::If(if_ast));
+                    status = ParserStatus::StatementParam;
+                    continue 'parse;
+                }
                 TokenType::KeywordSet => {
                     ast_vec.push(AST::If(if_ast));
                     status = ParserStatus::StatementSet;
                     continue 'parse;
                 }
+                TokenType::KeywordExport => {
+                    ast_vec.push(AST::If(if_ast));
+                    status = ParserStatus::StatementExport;
+                    continue 'parse;
+                }
                 TokenType::KeywordPrint => {
                     ast_vec.push(AST::If(if_ast));
                     status = ParserStatus::StatementPrint;
@@ -439,7 +626,12 @@ fn parse_statement(lexer: &mut Lexer, status: ParserStatus) -> Result<Vec<AST>,
                 }
                 TokenType::KeywordResult => {
                     ast_vec.push(AST::If(if_ast));
-                    status = ParserStatus::StatementResult;
+                    status = ParserStatus::StatementModifier(statement_token);
+                    continue 'parse;
+                }
+                TokenType::Id if is_modifier(&statement_token) => {
+                    ast_vec.push(AST::If(if_ast));
+                    status = ParserStatus::StatementModifier(statement_token);
                     continue 'parse;
                 }
                 TokenType::KeywordNonBlock => {
@@ -452,52 +644,72 @@ fn parse_statement(lexer: &mut Lexer, status: ParserStatus) -> Result<Vec<AST>,
                     status = ParserStatus::StatementFor;
                     continue 'parse;
                 }
+                TokenType::KeywordBreak => {
+                    ast_vec.push(AST::If(if_ast));
+                    status = ParserStatus::StatementBreak(statement_token);
+                    continue 'parse;
+                }
+                TokenType::KeywordContinue => {
+                    ast_vec.push(AST::If(if_ast));
+                    status = ParserStatus::StatementContinue(statement_token);
+                    continue 'parse;
+                }
                 TokenType::KeywordIf => {
                     ast_vec.push(AST::If(if_ast));
                     status = ParserStatus::StatementIf;
                     continue 'parse;
                 }
+                TokenType::KeywordAssert => {
+                    ast_vec.push(AST::If(if_ast));
+                    status = ParserStatus::StatementAssert(statement_token);
+                    continue 'parse;
+                }
+                TokenType::KeywordTest => {
+                    ast_vec.push(AST::If(if_ast));
+                    status = ParserStatus::StatementTest;
+                    continue 'parse;
+                }
                 TokenType::KeywordElse => {
                     status = ParserStatus::StatementIfNextElse(if_ast);
                     continue 'parse;
                 }
                 _ => {
-                    print_last_line_of_token(
-                        lexer,
+                    report(
+                        parser,
                         &statement_token,
-                        "A 'import', 'set', 'print', 'printErr', 'return', 'await', 'result', 'nonblock', 'for', 'break', 'continue', 'if' and 'else' keyword only can be used here.",
+                        "A 'import', 'param', 'set', 'export', 'print', 'printErr', 'return', 'await', 'result', 'retry', 'timeout', 'nonblock', 'for', 'break', 'continue', 'if', 'assert', 'test' and 'else' keyword only can be used here.",
                     );
                     return Err(());
                 }
             }
         } else if let ParserStatus::StatementIfNextElse(mut if_ast) = status {
-            let if_or_brace_token = next_lookahead(lexer)?;
+            let if_or_brace_token = next_lookahead(parser)?;
 
             match if_or_brace_token.token_type {
                 TokenType::KeywordIf => {
-                    next(lexer)?;
-                    let mut statement_vec = parse_statement(lexer, ParserStatus::StatementIf)?;
+                    next(parser)?;
+                    let mut statement_vec = parse_statement(parser, ParserStatus::StatementIf)?;
 
                     if statement_vec.is_empty() {
-                        print_last_line_of_token(
-                            lexer,
+                        report(
+                            parser,
                             &if_or_brace_token,
                             "This if statement is not fully closed; terminated unexpectedly.",
                         );
                         return Err(());
                     }
 
-                    if_ast.else_ast_vec = Some(vec![statement_vec.drain(0..).next().unwrap()]);
+                    if_ast.else_ast_vec = Some(vec![statement_vec.remove(0)]);
                     ast_vec.push(AST::If(if_ast));
                     ast_vec.extend(statement_vec);
                 }
                 TokenType::BraceL => {
-                    if_ast.else_ast_vec = Some(parse_block(lexer)?);
+                    if_ast.else_ast_vec = Some(parse_block(parser)?);
                     ast_vec.push(AST::If(if_ast));
                 }
                 _ => {
-                    print_last_line_of_token(
-                        lexer,
+                    report(
+                        parser,
                         &if_or_brace_token,
                         "An else statement should be followed by an if statement or a block statement.",
                     );
@@ -505,6 +717,34 @@ fn parse_statement(lexer: &mut Lexer, status: ParserStatus) -> Result<Vec<AST>,
                 }
             }
 
+            return Ok(ast_vec);
+        } else if let ParserStatus::StatementAssert(assert_token) = status {
+            let criteria = parse_expression(parser)?;
+            let message = if next_lookahead(parser)?.token_type == TokenType::Comma {
+                next(parser)?;
+                Some(parse_expression(parser)?)
+            } else {
+                None
+            };
+
+            next_token(parser, TokenType::Semicolon)?;
+
+            ast_vec.push(AST::Assert(AssertAST {
+                token: assert_token,
+                criteria,
+                message,
+            }));
+
+            return Ok(ast_vec);
+        } else if let ParserStatus::StatementTest = status {
+            let name_token = next_token(parser, TokenType::LiteralString)?;
+            let body_ast_vec = parse_block(parser)?;
+
+            ast_vec.push(AST::Test(TestAST {
+                name: name_token,
+                body_ast_vec,
+            }));
+
             return Ok(ast_vec);
         } else {
             unreachable!();
@@ -512,33 +752,90 @@ fn parse_statement(lexer: &mut Lexer, status: ParserStatus) -> Result<Vec<AST>,
     }
 }
 
-fn parse_if(lexer: &mut Lexer) -> Result<IfAST, ()> {
+fn parse_if(parser: &mut Parser) -> Result<IfAST, ()> {
     Ok(IfAST {
-        criteria: parse_expression(lexer)?,
-        if_ast_vec: parse_block(lexer)?,
+        criteria: parse_expression(parser)?,
+        if_ast_vec: parse_block(parser)?,
         else_ast_vec: None,
     })
 }
 
-fn parse_block(lexer: &mut Lexer) -> Result<Vec<AST>, ()> {
-    next_token(lexer, TokenType::BraceL)?;
-    let ast_vec = parse_statement_vec(lexer)?;
-    next_token(lexer, TokenType::BraceR)?;
+fn parse_block(parser: &mut Parser) -> Result<Vec<AST>, ()> {
+    next_token(parser, TokenType::BraceL)?;
+    let ast_vec = parse_statement_vec(parser);
+    next_token(parser, TokenType::BraceR)?;
 
     Ok(ast_vec)
 }
 
-fn parse_pipeline_result(lexer: &mut Lexer) -> Result<AST, ()> {
-    next_token(lexer, TokenType::KeywordAs)?;
-    let result_as = Some(next_token(lexer, TokenType::Id)?);
+fn is_modifier(token: &Token) -> bool {
+    token.token_type == TokenType::Id && MODIFIER_NAMES.contains(&token.token_content.as_str())
+}
+
+// Parses the modifiers of a pipeline invocation, e.g. `@retry 3 @timeout 60 @result as x`,
+// starting from the keyword of the first one, and the pipeline invocation they apply to.
+fn parse_pipeline_modifier(parser: &mut Parser, modifier_token: Token) -> Result<AST, ()> {
+    let mut result_as = None;
+    let mut retry = None;
+    let mut timeout = None;
+    let mut modifier_token = modifier_token;
+
+    loop {
+        let is_duplicated = match modifier_token.token_type {
+            TokenType::KeywordResult => {
+                next_token(parser, TokenType::KeywordAs)?;
+                result_as
+                    .replace(next_token(parser, TokenType::Id)?)
+                    .is_some()
+            }
+            TokenType::Id if modifier_token.token_content == "retry" => retry
+                .replace(ModifierAST {
+                    keyword: modifier_token.clone(),
+                    value: parse_expression(parser)?,
+                })
+                .is_some(),
+            TokenType::Id if modifier_token.token_content == "timeout" => timeout
+                .replace(ModifierAST {
+                    keyword: modifier_token.clone(),
+                    value: parse_expression(parser)?,
+                })
+                .is_some(),
+            _ => {
+                report(
+                    parser,
+                    &modifier_token,
+                    "A 'result', 'retry' and 'timeout' keyword only can be used here.",
+                );
+                return Err(());
+            }
+        };
+
+        if is_duplicated {
+            report(
+                parser,
+                &modifier_token,
+                "A modifier can be given only once for a pipeline invocation.",
+            );
+            return Err(());
+        }
+
+        if next_lookahead(parser)?.token_type != TokenType::At {
+            break;
+        }
+
+        next(parser)?;
+        modifier_token = next(parser)?;
+    }
 
-    let mut ast = parse_pipeline(lexer)?;
+    let mut ast = parse_pipeline(parser)?;
 
     if let AST::Pipeline(pipeline_ast) = &mut ast {
         pipeline_ast.result_as = result_as;
+        pipeline_ast.retry = retry;
+        pipeline_ast.timeout = timeout;
     } else if let AST::Call(call_ast) = &ast {
-        print_last_line_of_token(
-            lexer,
+        report(
+            parser,
             &call_ast.name,
             "An result statement should be followed by an pipeline statement.",
         );
@@ -550,36 +847,36 @@ fn parse_pipeline_result(lexer: &mut Lexer) -> Result<AST, ()> {
     Ok(ast)
 }
 
-fn parse_pipeline(lexer: &mut Lexer) -> Result<AST, ()> {
-    let id_token = next_token(lexer, TokenType::Id)?;
+fn parse_pipeline(parser: &mut Parser) -> Result<AST, ()> {
+    let id_token = next_token(parser, TokenType::Id)?;
 
-    if next_lookahead(lexer)?.token_type == TokenType::ParenL {
-        let call_ast = AST::Call(parse_call(lexer, id_token)?);
-        next_token(lexer, TokenType::Semicolon)?;
+    if next_lookahead(parser)?.token_type == TokenType::ParenL {
+        let call_ast = AST::Call(parse_call(parser, id_token)?);
+        next_token(parser, TokenType::Semicolon)?;
         return Ok(call_ast);
     }
 
     let mut argument_vec = Vec::new();
 
     loop {
-        let name_or_semicolon_token = next(lexer)?;
+        let name_or_semicolon_token = next(parser)?;
 
         if name_or_semicolon_token.token_type == TokenType::Semicolon {
             break;
         }
 
         if name_or_semicolon_token.token_type != TokenType::Id {
-            print_last_line_of_token(
-                lexer,
+            report(
+                parser,
                 &name_or_semicolon_token,
                 "An identifier or a semicolon only can be placed here.",
             );
             return Err(());
         }
 
-        next_token(lexer, TokenType::Equal)?;
+        next_token(parser, TokenType::Equal)?;
 
-        let expression_ast = parse_expression(lexer)?;
+        let expression_ast = parse_expression(parser)?;
 
         argument_vec.push((name_or_semicolon_token, expression_ast));
     }
@@ -587,38 +884,40 @@ fn parse_pipeline(lexer: &mut Lexer) -> Result<AST, ()> {
     Ok(AST::Pipeline {
         0: PipelineAST {
             result_as: None,
+            retry: None,
+            timeout: None,
             name: id_token,
             argument_vec,
         },
     })
 }
 
-fn parse_call(lexer: &mut Lexer, name_token: Token) -> Result<CallAST, ()> {
-    next_token(lexer, TokenType::ParenL)?;
+fn parse_call(parser: &mut Parser, name_token: Token) -> Result<CallAST, ()> {
+    next_token(parser, TokenType::ParenL)?;
 
     let mut expression_vec = Vec::new();
 
     loop {
-        let token = next_lookahead(lexer)?;
+        let token = next_lookahead(parser)?;
 
         if token.token_type == TokenType::ParenR {
             break;
         }
 
-        expression_vec.push(parse_expression(lexer)?);
+        expression_vec.push(parse_expression(parser)?);
 
-        let comma_or_parent_token = next_lookahead(lexer)?;
+        let comma_or_parent_token = next_lookahead(parser)?;
 
         match comma_or_parent_token.token_type {
             TokenType::Comma => {
-                next(lexer)?;
+                next(parser)?;
             }
             TokenType::ParenR => {
                 break;
             }
             _ => {
-                print_last_line_of_token(
-                    lexer,
+                report(
+                    parser,
                     &comma_or_parent_token,
                     "A comma or a right parenthesis only can be placed here.",
                 );
@@ -627,7 +926,7 @@ fn parse_call(lexer: &mut Lexer, name_token: Token) -> Result<CallAST, ()> {
         }
     }
 
-    next_token(lexer, TokenType::ParenR)?;
+    next_token(parser, TokenType::ParenR)?;
 
     Ok(CallAST {
         name: name_token,
@@ -635,35 +934,41 @@ fn parse_call(lexer: &mut Lexer, name_token: Token) -> Result<CallAST, ()> {
     })
 }
 
-fn parse_expression(lexer: &mut Lexer) -> Result<ExpressionAST, ()> {
-    let expression_token = next_lookahead(lexer)?;
+fn parse_expression(parser: &mut Parser) -> Result<ExpressionAST, ()> {
+    let expression_token = next_lookahead(parser)?;
 
     Ok(match expression_token.token_type {
         TokenType::LiteralBool => ExpressionAST::Literal {
-            0: LiteralAST::Bool { 0: next(lexer)? },
+            0: LiteralAST::Bool { 0: next(parser)? },
         },
         TokenType::LiteralInteger => ExpressionAST::Literal {
-            0: LiteralAST::Integer { 0: next(lexer)? },
+            0: LiteralAST::Integer { 0: next(parser)? },
         },
         TokenType::LiteralString => ExpressionAST::Literal {
-            0: LiteralAST::String { 0: next(lexer)? },
+            0: LiteralAST::String { 0: next(parser)? },
         },
         TokenType::Id => {
-            let name_token = next(lexer)?;
+            let name_token = next(parser)?;
 
-            if next_lookahead(lexer)?.token_type == TokenType::ParenL {
+            if next_lookahead(parser)?.token_type == TokenType::ParenL {
                 ExpressionAST::Call {
-                    0: parse_call(lexer, name_token)?,
+                    0: parse_call(parser, name_token)?,
                 }
+            } else if next_lookahead(parser)?.token_type == TokenType::Dot {
+                next(parser)?;
+                ExpressionAST::Member(MemberAST {
+                    module: name_token,
+                    name: next_token(parser, TokenType::Id)?,
+                })
             } else {
                 ExpressionAST::Variable { 0: name_token }
             }
         }
-        TokenType::BracketL => parse_array(lexer)?,
-        TokenType::BraceL => parse_dict(lexer)?,
+        TokenType::BracketL => parse_array(parser)?,
+        TokenType::BraceL => parse_dict(parser)?,
         _ => {
-            print_last_line_of_token(
-                lexer,
+            report(
+                parser,
                 &expression_token,
                 "An expression only can be placed here.",
             );
@@ -672,32 +977,32 @@ fn parse_expression(lexer: &mut Lexer) -> Result<ExpressionAST, ()> {
     })
 }
 
-fn parse_array(lexer: &mut Lexer) -> Result<ExpressionAST, ()> {
-    next_token(lexer, TokenType::BracketL)?;
+fn parse_array(parser: &mut Parser) -> Result<ExpressionAST, ()> {
+    next_token(parser, TokenType::BracketL)?;
 
     let mut expression_vec = Vec::new();
 
     loop {
-        let token = next_lookahead(lexer)?;
+        let token = next_lookahead(parser)?;
 
         if token.token_type == TokenType::BracketR {
             break;
         }
 
-        expression_vec.push(parse_expression(lexer)?);
+        expression_vec.push(parse_expression(parser)?);
 
-        let comma_or_bracket_token = next_lookahead(lexer)?;
+        let comma_or_bracket_token = next_lookahead(parser)?;
 
         match comma_or_bracket_token.token_type {
             TokenType::Comma => {
-                next(lexer)?;
+                next(parser)?;
             }
             TokenType::BracketR => {
                 break;
             }
             _ => {
-                print_last_line_of_token(
-                    lexer,
+                report(
+                    parser,
                     &comma_or_bracket_token,
                     "A comma or a right bracket only can be placed here.",
                 );
@@ -706,18 +1011,18 @@ fn parse_array(lexer: &mut Lexer) -> Result<ExpressionAST, ()> {
         }
     }
 
-    next_token(lexer, TokenType::BracketR)?;
+    next_token(parser, TokenType::BracketR)?;
 
     Ok(ExpressionAST::Array { 0: expression_vec })
 }
 
-fn parse_dict(lexer: &mut Lexer) -> Result<ExpressionAST, ()> {
-    next_token(lexer, TokenType::BraceL)?;
+fn parse_dict(parser: &mut Parser) -> Result<ExpressionAST, ()> {
+    next_token(parser, TokenType::BraceL)?;
 
     let mut expression_map = HashMap::new();
 
     loop {
-        let brace_or_name_token = next_lookahead(lexer)?;
+        let brace_or_name_token = next_lookahead(parser)?;
 
         if brace_or_name_token.token_type == TokenType::BraceR {
             break;
@@ -726,34 +1031,34 @@ fn parse_dict(lexer: &mut Lexer) -> Result<ExpressionAST, ()> {
         if brace_or_name_token.token_type != TokenType::LiteralString
             && brace_or_name_token.token_type != TokenType::Id
         {
-            print_last_line_of_token(
-                lexer,
+            report(
+                parser,
                 &brace_or_name_token,
                 "An identifier or a string literal only can be placed here.",
             );
             return Err(());
         }
 
-        next(lexer)?;
-        next_token(lexer, TokenType::Colon)?;
+        next(parser)?;
+        next_token(parser, TokenType::Colon)?;
 
         expression_map.insert(
             brace_or_name_token.token_content.clone(),
-            (brace_or_name_token, parse_expression(lexer)?),
+            (brace_or_name_token, parse_expression(parser)?),
         );
 
-        let comma_or_brace_token = next_lookahead(lexer)?;
+        let comma_or_brace_token = next_lookahead(parser)?;
 
         match comma_or_brace_token.token_type {
             TokenType::Comma => {
-                next(lexer)?;
+                next(parser)?;
             }
             TokenType::BraceR => {
                 break;
             }
             _ => {
-                print_last_line_of_token(
-                    lexer,
+                report(
+                    parser,
                     &comma_or_brace_token,
                     "A comma or a right brace only can be placed here.",
                 );
@@ -762,139 +1067,192 @@ fn parse_dict(lexer: &mut Lexer) -> Result<ExpressionAST, ()> {
         }
     }
 
-    next_token(lexer, TokenType::BraceR)?;
+    next_token(parser, TokenType::BraceR)?;
 
     Ok(ExpressionAST::Dictionary { 0: expression_map })
 }
 
-fn next_lookahead(lexer: &mut Lexer) -> Result<Token, ()> {
+// Skips tokens until the end of the current statement; a semicolon or a whole block closes it,
+// and an unmatched right brace is left for the enclosing block.
+fn synchronize(parser: &mut Parser) {
+    let mut depth = 0;
+
+    loop {
+        let token = match parser.lexer.next_lookahead() {
+            Ok(token) => token,
+            Err(err) => {
+                handle_lexer_error(parser, err);
+                parser.lexer.next().ok();
+                continue;
+            }
+        };
+
+        match token.token_type {
+            TokenType::Eof => return,
+            TokenType::Semicolon if depth == 0 => {
+                parser.lexer.next().ok();
+                parser.recovering = false;
+                return;
+            }
+            TokenType::BraceL => depth += 1,
+            TokenType::BraceR => {
+                if depth == 0 {
+                    return;
+                }
+
+                depth -= 1;
+
+                if depth == 0 {
+                    parser.lexer.next().ok();
+                    parser.recovering = false;
+                    return;
+                }
+            }
+            _ => {}
+        }
+
+        parser.lexer.next().ok();
+    }
+}
+
+fn next_lookahead(parser: &mut Parser) -> Result<Token, ()> {
     loop {
-        match lexer.next_lookahead() {
+        match parser.lexer.next_lookahead() {
             Ok(token) => {
                 if token.token_type == TokenType::Comment {
-                    lexer.next().map_err(|_| ())?;
+                    parser.lexer.next().map_err(|_| ())?;
                     continue;
                 }
 
                 return Ok(token);
             }
             Err(err) => {
-                handle_lexer_error(lexer, err);
+                handle_lexer_error(parser, err);
                 return Err(());
             }
         }
     }
 }
 
-fn next(lexer: &mut Lexer) -> Result<Token, ()> {
+fn next(parser: &mut Parser) -> Result<Token, ()> {
     loop {
-        match lexer.next() {
+        match parser.lexer.next() {
             Ok(token) => {
                 if token.token_type == TokenType::Comment {
-                    lexer.next().map_err(|_| ())?;
                     continue;
                 }
 
                 return Ok(token);
             }
             Err(err) => {
-                handle_lexer_error(lexer, err);
+                handle_lexer_error(parser, err);
                 return Err(());
             }
         }
     }
 }
 
-fn next_token(lexer: &mut Lexer, token_type: TokenType) -> Result<Token, ()> {
-    let token = next(lexer)?;
+fn next_token(parser: &mut Parser, token_type: TokenType) -> Result<Token, ()> {
+    let token = next(parser)?;
 
     if token.token_type != token_type {
-        print_last_line_of_token(
-            lexer,
+        report(
+            parser,
             &token,
             &format!("It is not allowed here; {:#?} expected.", token_type),
-        )
+        );
+        return Err(());
     }
 
     Ok(token)
 }
 
-fn handle_lexer_error(lexer: &Lexer, err: LexerError) {
+fn handle_lexer_error(parser: &mut Parser, err: LexerError) {
     match err {
         LexerError::StringNotClosed(token) => {
-            print_last_line_of_token(lexer, &token, "String literals should be closed with \".");
+            report(parser, &token, "String literals should be closed with \".");
         }
         LexerError::WhitespaceEscapeSequence(token) => {
-            print_last_line_of_token(
-                lexer,
+            report(
+                parser,
                 &token,
                 "Whitespaces are not allowed for escape sequences.",
             );
         }
         LexerError::UnexpectedCharacter(token) => {
-            print_last_line_of_token(lexer, &token, "Remove it, this character is not allowed.");
+            report(parser, &token, "Remove it, this character is not allowed.");
         }
     }
 }
 
-fn print_last_line_of_token(lexer: &Lexer, token: &Token, message: &str) {
-    let actual_token_content = token.token_content.trim_end();
-    let mut actual_len = actual_token_content.len();
-    let begin_index = match actual_token_content.rfind('\n') {
-        Some(index) => index + 1,
-        None => 0,
-    };
+fn report(parser: &mut Parser, token: &Token, message: &str) {
+    if parser.recovering {
+        return;
+    }
 
-    if token.token_type == TokenType::LiteralString {
-        actual_len += 2;
+    parser.diagnostic_vec.push(Diagnostic::new(token, message));
+    parser.recovering = true;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostics(content: &str) -> Vec<(usize, usize, String)> {
+        let mut lexer = Lexer::new("test.piped".to_owned(), content.to_owned());
+
+        match parse(&mut lexer) {
+            Ok(..) => Vec::new(),
+            Err(diagnostic_vec) => diagnostic_vec
+                .into_iter()
+                .map(|diagnostic| {
+                    (
+                        diagnostic.line_number,
+                        diagnostic.line_offset,
+                        diagnostic.message,
+                    )
+                })
+                .collect(),
+        }
     }
 
-    let max_line_number = lexer.src_content().len();
-    let max_line_number_width = (max_line_number as f64).log(10f64).ceil() as usize;
-
-    println!(
-        "{}:{}:{}",
-        token.file_path, token.line_number, token.line_offset
-    );
-    if 2 <= token.line_number {
-        println!(
-            "{:>width$} | {}",
-            token.line_number - 1,
-            lexer.src_content()[token.line_number - 2],
-            width = max_line_number_width
+    #[test]
+    fn reports_every_statement_with_an_error() {
+        let diagnostic_vec =
+            diagnostics("@set = 1;\n@set a = 1;\n@set c = ;\n@set b = 2\n@print b;\n");
+
+        assert_eq!(
+            diagnostic_vec
+                .iter()
+                .map(|(line_number, ..)| *line_number)
+                .collect::<Vec<_>>(),
+            vec![1, 3, 5]
         );
     }
-    if token.line_number <= lexer.src_content().len() {
-        println!(
-            "{:>width$} | {}",
-            token.line_number,
-            lexer.src_content()[token.line_number - 1],
-            width = max_line_number_width
-        );
-    } else {
-        println!(
-            "{:>width$} | ",
-            token.line_number,
-            width = max_line_number_width
+
+    #[test]
+    fn reports_an_error_only_once() {
+        assert_eq!(
+            diagnostics("@print \"x\" }\n"),
+            vec![(1, 12, "An expression only can be placed here.".to_owned())]
         );
     }
-    println!(
-        "{}{} {}",
-        &repeat(" ")
-            .take(max_line_number_width + 3 + token.line_offset + begin_index - 1)
-            .collect::<String>(),
-        &repeat("^")
-            .take(max(1, actual_len - begin_index))
-            .collect::<String>(),
-        message
-    );
-    if token.line_number < lexer.src_content().len() {
-        println!(
-            "{:>width$} | {}",
-            token.line_number + 1,
-            lexer.src_content()[token.line_number],
-            width = max_line_number_width
+
+    #[test]
+    fn recovers_after_a_block() {
+        let diagnostic_vec = diagnostics("@if true {\n  @set a = ;\n}\n@set = 1;\n");
+
+        assert_eq!(
+            diagnostic_vec
+                .iter()
+                .map(|(line_number, ..)| *line_number)
+                .collect::<Vec<_>>(),
+            vec![2, 4]
         );
     }
-    println!("");
+
+    #[test]
+    fn parses_a_valid_input_without_diagnostics() {
+        assert!(diagnostics("@set a = [1, 2];\n@if a {\n  @print a;\n}\n").is_empty());
+    }
 }
@@ -0,0 +1,495 @@
+use super::super::compiler::diagnostic::Diagnostic;
+use super::super::compiler::lookahead_lexer::LookaheadLexer as Lexer;
+use super::super::compiler::parser::{parse, AST};
+use super::super::runtime::builtins::functions::function::build_function_map;
+use super::super::runtime::builtins::pipelines::pipeline::build_pipeline_map;
+use super::super::runtime::imported_pipeline::resolve_import_path;
+use super::symbol::{SymbolIndex, SymbolKind};
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use lsp_types::{
+	CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
+	DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+	GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents, HoverParams,
+	HoverProviderCapability, Location, MarkupContent, MarkupKind, OneOf, Position,
+	PublishDiagnosticsParams, Range, ServerCapabilities, TextDocumentSyncCapability,
+	TextDocumentSyncKind, Url,
+};
+use std::collections::HashMap;
+use std::error::Error;
+
+const FUNCTION_DOCS: &str = include_str!("../../docs/built-in-functions.md");
+const PIPELINE_DOCS: &str = include_str!("../../docs/built-in-pipelines.md");
+
+struct Server {
+	connection: Connection,
+	document_map: HashMap<Url, String>,
+	function_doc_map: HashMap<String, String>,
+	pipeline_doc_map: HashMap<String, String>,
+}
+
+pub fn run() -> Result<(), Box<dyn Error + Sync + Send>> {
+	let (connection, io_threads) = Connection::stdio();
+	let capabilities = ServerCapabilities {
+		text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+		definition_provider: Some(OneOf::Left(true)),
+		hover_provider: Some(HoverProviderCapability::Simple(true)),
+		completion_provider: Some(CompletionOptions::default()),
+		..ServerCapabilities::default()
+	};
+
+	connection.initialize(serde_json::to_value(&capabilities)?)?;
+
+	let mut server = Server {
+		connection,
+		document_map: HashMap::new(),
+		function_doc_map: parse_doc_sections(FUNCTION_DOCS),
+		pipeline_doc_map: parse_doc_sections(PIPELINE_DOCS),
+	};
+
+	server.serve()?;
+	drop(server);
+	io_threads.join()?;
+
+	Ok(())
+}
+
+impl Server {
+	fn serve(&mut self) -> Result<(), Box<dyn Error + Sync + Send>> {
+		while let Ok(message) = self.connection.receiver.recv() {
+			match message {
+				Message::Request(request) => {
+					if self.connection.handle_shutdown(&request)? {
+						return Ok(());
+					}
+
+					self.handle_request(request)?;
+				}
+				Message::Notification(notification) => self.handle_notification(notification)?,
+				Message::Response(..) => {}
+			}
+		}
+
+		Ok(())
+	}
+
+	fn handle_request(&mut self, request: Request) -> Result<(), Box<dyn Error + Sync + Send>> {
+		let id = request.id.clone();
+
+		match request.method.as_str() {
+			"textDocument/definition" => {
+				let params: GotoDefinitionParams = match serde_json::from_value(request.params) {
+					Ok(params) => params,
+					Err(err) => return self.respond_invalid_params(id, err),
+				};
+				let result = self.definition(params);
+				self.respond(id, serde_json::to_value(result)?)
+			}
+			"textDocument/hover" => {
+				let params: HoverParams = match serde_json::from_value(request.params) {
+					Ok(params) => params,
+					Err(err) => return self.respond_invalid_params(id, err),
+				};
+				let result = self.hover(params);
+				self.respond(id, serde_json::to_value(result)?)
+			}
+			"textDocument/completion" => {
+				let params: CompletionParams = match serde_json::from_value(request.params) {
+					Ok(params) => params,
+					Err(err) => return self.respond_invalid_params(id, err),
+				};
+				let result = self.completion(params);
+				self.respond(id, serde_json::to_value(result)?)
+			}
+			_ => {
+				self.connection
+					.sender
+					.send(Message::Response(Response::new_err(
+						id,
+						lsp_server::ErrorCode::MethodNotFound as i32,
+						format!("unsupported method '{}'", request.method),
+					)))?;
+				Ok(())
+			}
+		}
+	}
+
+	fn handle_notification(
+		&mut self,
+		notification: Notification,
+	) -> Result<(), Box<dyn Error + Sync + Send>> {
+		match notification.method.as_str() {
+			"textDocument/didOpen" => {
+				let params: DidOpenTextDocumentParams =
+					match serde_json::from_value(notification.params) {
+						Ok(params) => params,
+						Err(err) => return self.ignore_invalid_params(&notification.method, err),
+					};
+				self.document_map
+					.insert(params.text_document.uri.clone(), params.text_document.text);
+				self.publish_diagnostics(params.text_document.uri)
+			}
+			"textDocument/didChange" => {
+				let params: DidChangeTextDocumentParams =
+					match serde_json::from_value(notification.params) {
+						Ok(params) => params,
+						Err(err) => return self.ignore_invalid_params(&notification.method, err),
+					};
+
+				if let Some(change) = params.content_changes.into_iter().last() {
+					self.document_map
+						.insert(params.text_document.uri.clone(), change.text);
+				}
+
+				self.publish_diagnostics(params.text_document.uri)
+			}
+			"textDocument/didClose" => {
+				let params: DidCloseTextDocumentParams =
+					match serde_json::from_value(notification.params) {
+						Ok(params) => params,
+						Err(err) => return self.ignore_invalid_params(&notification.method, err),
+					};
+				self.document_map.remove(&params.text_document.uri);
+				self.send_diagnostics(params.text_document.uri, Vec::new())
+			}
+			_ => Ok(()),
+		}
+	}
+
+	fn respond(
+		&self,
+		id: RequestId,
+		result: serde_json::Value,
+	) -> Result<(), Box<dyn Error + Sync + Send>> {
+		self.connection
+			.sender
+			.send(Message::Response(Response::new_ok(id, result)))?;
+		Ok(())
+	}
+
+	// Malformed params only fail the request they came with, the server keeps serving.
+	fn respond_invalid_params(
+		&self,
+		id: RequestId,
+		err: serde_json::Error,
+	) -> Result<(), Box<dyn Error + Sync + Send>> {
+		self.connection
+			.sender
+			.send(Message::Response(Response::new_err(
+				id,
+				lsp_server::ErrorCode::InvalidParams as i32,
+				format!("invalid params: {}", err),
+			)))?;
+		Ok(())
+	}
+
+	// Notifications can not be answered, so malformed ones are only reported.
+	fn ignore_invalid_params(
+		&self,
+		method: &str,
+		err: serde_json::Error,
+	) -> Result<(), Box<dyn Error + Sync + Send>> {
+		eprintln!(
+			"Unable to handle the notification '{}'; it is ignored.",
+			method
+		);
+		eprintln!("\tbecause: {}", err);
+		Ok(())
+	}
+
+	fn parse(&self, uri: &Url) -> Result<Vec<AST>, Vec<Diagnostic>> {
+		let content = self.document_map.get(uri).cloned().unwrap_or_default();
+		parse(&mut Lexer::new(uri.path().to_owned(), content))
+	}
+
+	fn publish_diagnostics(&self, uri: Url) -> Result<(), Box<dyn Error + Sync + Send>> {
+		let diagnostic_vec = match self.parse(&uri) {
+			Ok(..) => Vec::new(),
+			Err(diagnostic_vec) => diagnostic_vec
+				.into_iter()
+				.map(|diagnostic| lsp_types::Diagnostic {
+					range: Range::new(
+						Position::new(
+							diagnostic.line_number as u32 - 1,
+							diagnostic.line_offset as u32 - 1,
+						),
+						Position::new(
+							diagnostic.line_number as u32 - 1,
+							(diagnostic.line_offset + diagnostic.length) as u32 - 1,
+						),
+					),
+					severity: Some(lsp_types::DiagnosticSeverity::ERROR),
+					source: Some("piped".to_owned()),
+					message: diagnostic.message,
+					..lsp_types::Diagnostic::default()
+				})
+				.collect(),
+		};
+
+		self.send_diagnostics(uri, diagnostic_vec)
+	}
+
+	fn send_diagnostics(
+		&self,
+		uri: Url,
+		diagnostic_vec: Vec<lsp_types::Diagnostic>,
+	) -> Result<(), Box<dyn Error + Sync + Send>> {
+		let params = PublishDiagnosticsParams::new(uri, diagnostic_vec, None);
+
+		self.connection
+			.sender
+			.send(Message::Notification(Notification::new(
+				"textDocument/publishDiagnostics".to_owned(),
+				params,
+			)))?;
+		Ok(())
+	}
+
+	fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+		let uri = params.text_document_position_params.text_document.uri;
+		let position = params.text_document_position_params.position;
+		let index = SymbolIndex::build(&self.parse(&uri).ok()?);
+		let reference =
+			index.reference_at(position.line as usize + 1, position.character as usize + 1)?;
+
+		let (token, import_path) = match reference.kind {
+			SymbolKind::ImportPath => (&reference.token, Some(&reference.token.token_content)),
+			_ => {
+				let definition = index.definition_of(reference)?;
+				(&definition.token, definition.import_path.as_ref())
+			}
+		};
+
+		if let Some(import_path) = import_path {
+			let path = resolve_import_path(&uri.to_file_path().ok()?, import_path).ok()?;
+
+			if path.is_file() {
+				return Some(GotoDefinitionResponse::Scalar(Location::new(
+					Url::from_file_path(path).ok()?,
+					Range::default(),
+				)));
+			}
+		}
+
+		Some(GotoDefinitionResponse::Scalar(Location::new(
+			uri,
+			token_range(token),
+		)))
+	}
+
+	fn hover(&self, params: HoverParams) -> Option<Hover> {
+		let uri = params.text_document_position_params.text_document.uri;
+		let position = params.text_document_position_params.position;
+		let index = SymbolIndex::build(&self.parse(&uri).ok()?);
+		let reference =
+			index.reference_at(position.line as usize + 1, position.character as usize + 1)?;
+		let doc = match reference.kind {
+			SymbolKind::Function => self
+				.function_doc_map
+				.get(&reference.token.token_content)
+				.cloned()
+				.unwrap_or_else(|| {
+					format!(
+						"`{}(...)`\n\nBuilt-in function.",
+						reference.token.token_content
+					)
+				}),
+			SymbolKind::Pipeline => self
+				.pipeline_doc_map
+				.get(&reference.token.token_content)?
+				.clone(),
+			_ => return None,
+		};
+
+		Some(Hover {
+			contents: HoverContents::Markup(MarkupContent {
+				kind: MarkupKind::Markdown,
+				value: doc,
+			}),
+			range: Some(token_range(&reference.token)),
+		})
+	}
+
+	fn completion(&self, params: CompletionParams) -> Option<CompletionResponse> {
+		let mut item_vec = Vec::new();
+
+		for (name, function) in build_function_map().iter() {
+			item_vec.push(CompletionItem {
+				label: name.clone(),
+				kind: Some(CompletionItemKind::FUNCTION),
+				detail: Some(match function.argument_count() {
+					Some(count) => format!("built-in function; {} argument(s)", count),
+					None => "built-in function".to_owned(),
+				}),
+				..CompletionItem::default()
+			});
+		}
+
+		for name in build_pipeline_map().keys() {
+			item_vec.push(CompletionItem {
+				label: name.clone(),
+				kind: Some(CompletionItemKind::MODULE),
+				detail: Some("built-in pipeline".to_owned()),
+				..CompletionItem::default()
+			});
+		}
+
+		if let Ok(ast_vec) = self.parse(&params.text_document_position.text_document.uri) {
+			let index = SymbolIndex::build(&ast_vec);
+
+			for definition in index.definition_vec.iter() {
+				if item_vec
+					.iter()
+					.any(|item| item.label == definition.token.token_content)
+				{
+					continue;
+				}
+
+				item_vec.push(CompletionItem {
+					label: definition.token.token_content.clone(),
+					kind: Some(match definition.kind {
+						SymbolKind::Pipeline => CompletionItemKind::MODULE,
+						_ => CompletionItemKind::VARIABLE,
+					}),
+					..CompletionItem::default()
+				});
+			}
+		}
+
+		item_vec.sort_by(|left, right| left.label.cmp(&right.label));
+		Some(CompletionResponse::Array(item_vec))
+	}
+}
+
+fn token_range(token: &super::super::compiler::lexer::Token) -> Range {
+	Range::new(
+		Position::new(token.line_number as u32 - 1, token.line_offset as u32 - 1),
+		Position::new(
+			token.line_number as u32 - 1,
+			(token.line_offset + token.token_content.chars().count()) as u32 - 1,
+		),
+	)
+}
+
+// Splits a built-in document into sections keyed by the name in their "### `name(...)`" heading.
+fn parse_doc_sections(doc: &str) -> HashMap<String, String> {
+	doc.split("\n---\n")
+		.filter_map(|section| {
+			let heading = section.lines().find(|line| line.starts_with("### `"))?;
+			let name = heading["### `".len()..].split(['(', '`']).next()?;
+
+			Some((name.to_owned(), section.trim().to_owned()))
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn keeps_serving_after_a_request_with_invalid_params() {
+		let (connection, client) = Connection::memory();
+		let mut server = Server {
+			connection,
+			document_map: HashMap::new(),
+			function_doc_map: HashMap::new(),
+			pipeline_doc_map: HashMap::new(),
+		};
+
+		for (id, params) in [
+			(1, serde_json::json!({ "position": "nowhere" })),
+			(
+				2,
+				serde_json::json!({
+					"textDocument": { "uri": "file:///test.piped" },
+					"position": { "line": 0, "character": 0 },
+				}),
+			),
+		] {
+			client
+				.sender
+				.send(Message::Request(Request::new(
+					RequestId::from(id),
+					"textDocument/hover".to_owned(),
+					params,
+				)))
+				.unwrap();
+		}
+
+		drop(client.sender);
+		server.serve().unwrap();
+
+		let response_vec = client
+			.receiver
+			.try_iter()
+			.filter_map(|message| match message {
+				Message::Response(response) => Some(response),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+
+		assert_eq!(response_vec.len(), 2);
+		assert_eq!(
+			response_vec[0].error.as_ref().map(|error| error.code),
+			Some(lsp_server::ErrorCode::InvalidParams as i32)
+		);
+		assert_eq!(response_vec[1].id, RequestId::from(2));
+		assert!(response_vec[1].error.is_none());
+	}
+
+	#[test]
+	fn keeps_serving_after_a_notification_with_invalid_params() {
+		let (connection, client) = Connection::memory();
+		let mut server = Server {
+			connection,
+			document_map: HashMap::new(),
+			function_doc_map: HashMap::new(),
+			pipeline_doc_map: HashMap::new(),
+		};
+
+		for (method, params) in [
+			(
+				"textDocument/didOpen",
+				serde_json::json!({ "textDocument": 1 }),
+			),
+			("textDocument/didChange", serde_json::json!([])),
+			("textDocument/didClose", serde_json::json!(null)),
+			(
+				"textDocument/didOpen",
+				serde_json::json!({
+					"textDocument": {
+						"uri": "file:///test.piped",
+						"languageId": "piped",
+						"version": 1,
+						"text": "@print 1;",
+					},
+				}),
+			),
+		] {
+			client
+				.sender
+				.send(Message::Notification(Notification::new(
+					method.to_owned(),
+					params,
+				)))
+				.unwrap();
+		}
+
+		drop(client.sender);
+		server.serve().unwrap();
+
+		let method_vec = client
+			.receiver
+			.try_iter()
+			.filter_map(|message| match message {
+				Message::Notification(notification) => Some(notification.method),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+
+		assert_eq!(
+			method_vec,
+			vec!["textDocument/publishDiagnostics".to_owned()]
+		);
+	}
+}
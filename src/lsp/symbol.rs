@@ -0,0 +1,209 @@
+use super::super::compiler::lexer::Token;
+use super::super::compiler::parser::{ExpressionAST, LiteralAST, PipelineAST, AST};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SymbolKind {
+	Variable,
+	Pipeline,
+	Function,
+	ImportPath,
+}
+
+pub struct Definition {
+	pub kind: SymbolKind,
+	pub token: Token,
+	// The path literal of the import statement that defined this pipeline, if any.
+	pub import_path: Option<String>,
+}
+
+pub struct Reference {
+	pub kind: SymbolKind,
+	pub token: Token,
+}
+
+pub struct SymbolIndex {
+	pub definition_vec: Vec<Definition>,
+	pub reference_vec: Vec<Reference>,
+}
+
+impl SymbolIndex {
+	pub fn build(ast_vec: &[AST]) -> SymbolIndex {
+		let mut index = SymbolIndex {
+			definition_vec: Vec::new(),
+			reference_vec: Vec::new(),
+		};
+
+		index.collect(ast_vec);
+		index
+	}
+
+	// Finds a reference under the given position. Both are 1-based like token positions.
+	pub fn reference_at(&self, line_number: usize, line_offset: usize) -> Option<&Reference> {
+		self.reference_vec.iter().find(|reference| {
+			let token = &reference.token;
+			let length = token.token_content.chars().count()
+				+ if reference.kind == SymbolKind::ImportPath {
+					2
+				} else {
+					0
+				};
+
+			token.line_number == line_number
+				&& token.line_offset <= line_offset
+				&& line_offset <= token.line_offset + length
+		})
+	}
+
+	// Finds the definition a reference most likely refers to; the last one written before it,
+	// or the first one after it if there is none (e.g. a variable set later in a loop).
+	pub fn definition_of(&self, reference: &Reference) -> Option<&Definition> {
+		let position = (reference.token.line_number, reference.token.line_offset);
+		let mut candidate_vec = self.definition_vec.iter().filter(|definition| {
+			definition.kind == reference.kind
+				&& definition.token.token_content == reference.token.token_content
+		});
+
+		candidate_vec
+			.clone()
+			.rfind(|definition| {
+				(definition.token.line_number, definition.token.line_offset) <= position
+			})
+			.or_else(|| candidate_vec.next())
+	}
+
+	fn define(&mut self, kind: SymbolKind, token: &Token, import_path: Option<String>) {
+		self.definition_vec.push(Definition {
+			kind,
+			token: token.clone(),
+			import_path,
+		});
+	}
+
+	fn refer(&mut self, kind: SymbolKind, token: &Token) {
+		self.reference_vec.push(Reference {
+			kind,
+			token: token.clone(),
+		});
+	}
+
+	fn collect(&mut self, ast_vec: &[AST]) {
+		for ast in ast_vec.iter() {
+			match ast {
+				AST::Import(import_ast) => {
+					let import_path = match &import_ast.path {
+						ExpressionAST::Literal(LiteralAST::String(path_token)) => {
+							self.refer(SymbolKind::ImportPath, path_token);
+							Some(path_token.token_content.clone())
+						}
+						path => {
+							self.collect_expression(path);
+							None
+						}
+					};
+
+					self.define(SymbolKind::Pipeline, &import_ast.name, import_path);
+				}
+				AST::Param(param_ast) => {
+					if let Some(default) = &param_ast.default {
+						self.collect_expression(default);
+					}
+
+					self.define(SymbolKind::Variable, &param_ast.name, None);
+				}
+				AST::Set(set_ast) | AST::Export(set_ast) => {
+					self.collect_expression(&set_ast.value);
+					self.define(SymbolKind::Variable, &set_ast.name, None);
+				}
+				AST::Print(print_ast) => {
+					for expression_ast in print_ast.expression_vec.iter() {
+						self.collect_expression(expression_ast);
+					}
+				}
+				AST::PrintErr(print_err_ast) => {
+					for expression_ast in print_err_ast.expression_vec.iter() {
+						self.collect_expression(expression_ast);
+					}
+				}
+				AST::Return(return_ast) => {
+					if let Some(value) = &return_ast.value {
+						self.collect_expression(value);
+					}
+				}
+				AST::Await(..) | AST::AwaitAll | AST::Break(..) | AST::Continue(..) => {}
+				AST::NonBlock(non_block_ast) => self.collect_pipeline(&non_block_ast.pipeline),
+				AST::For(for_ast) => {
+					self.collect_expression(&for_ast.variable_in);
+					self.define(SymbolKind::Variable, &for_ast.variable_name, None);
+					self.collect(&for_ast.body_ast_vec);
+				}
+				AST::If(if_ast) => {
+					self.collect_expression(&if_ast.criteria);
+					self.collect(&if_ast.if_ast_vec);
+
+					if let Some(else_ast_vec) = &if_ast.else_ast_vec {
+						self.collect(else_ast_vec);
+					}
+				}
+				AST::Assert(assert_ast) => {
+					self.collect_expression(&assert_ast.criteria);
+
+					if let Some(message) = &assert_ast.message {
+						self.collect_expression(message);
+					}
+				}
+				AST::Test(test_ast) => self.collect(&test_ast.body_ast_vec),
+				AST::Pipeline(pipeline_ast) => self.collect_pipeline(pipeline_ast),
+				AST::Call(call_ast) => {
+					self.refer(SymbolKind::Function, &call_ast.name);
+
+					for expression_ast in call_ast.argument_vec.iter() {
+						self.collect_expression(expression_ast);
+					}
+				}
+			}
+		}
+	}
+
+	fn collect_pipeline(&mut self, pipeline_ast: &PipelineAST) {
+		self.refer(SymbolKind::Pipeline, &pipeline_ast.name);
+
+		for modifier_ast in pipeline_ast.retry.iter().chain(pipeline_ast.timeout.iter()) {
+			self.collect_expression(&modifier_ast.value);
+		}
+
+		for (_, expression_ast) in pipeline_ast.argument_vec.iter() {
+			self.collect_expression(expression_ast);
+		}
+
+		if let Some(result_as) = &pipeline_ast.result_as {
+			self.define(SymbolKind::Variable, result_as, None);
+		}
+	}
+
+	fn collect_expression(&mut self, expression_ast: &ExpressionAST) {
+		match expression_ast {
+			ExpressionAST::Array(array) => {
+				for element in array.iter() {
+					self.collect_expression(element);
+				}
+			}
+			ExpressionAST::Dictionary(dictionary) => {
+				for (_, (_, value)) in dictionary.iter() {
+					self.collect_expression(value);
+				}
+			}
+			ExpressionAST::Literal(..) => {}
+			ExpressionAST::Variable(token) => self.refer(SymbolKind::Variable, token),
+			ExpressionAST::Member(member_ast) => {
+				self.refer(SymbolKind::Pipeline, &member_ast.module)
+			}
+			ExpressionAST::Call(call_ast) => {
+				self.refer(SymbolKind::Function, &call_ast.name);
+
+				for expression_ast in call_ast.argument_vec.iter() {
+					self.collect_expression(expression_ast);
+				}
+			}
+		}
+	}
+}
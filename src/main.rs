@@ -1,27 +1,304 @@
+mod analysis;
 mod compiler;
+mod lsp;
+mod package;
 mod runtime;
 
-use clap::{App, Arg};
+use analysis::checker::Checker;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use compiler::ast_dump::dump;
+use compiler::formatter::format;
+use package::fetch::fetch;
+use runtime::action_cache::{format_size, set_cache_enabled, ActionCache};
 use runtime::execution::Execution;
-use runtime::imported_pipeline::ImportedPipeline;
-use std::path::PathBuf;
+use runtime::execution_log::LogFormat;
+use runtime::imported_pipeline::{ImportError, ImportedPipeline};
+use runtime::output::{set_output_options, OutputMode};
+use runtime::project_config::{ProjectConfig, PROJECT_CONFIG_FILE_NAME};
+use runtime::remote_cache::set_remote_cache;
+use runtime::repl::repl;
+use runtime::signal::{handle_signals, park_if_interrupted};
+use runtime::test_runner::run_tests;
+use runtime::watch::watch;
+use std::env::{current_dir, var};
+use std::fs::{read_to_string, write, File};
+use std::path::{Path, PathBuf};
 use std::process::exit;
+use std::time::Duration;
 
 fn main() {
     let matches = App::new("piped")
         .version(env!("CARGO_PKG_VERSION"))
         .about(env!("CARGO_PKG_DESCRIPTION"))
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .setting(AppSettings::ArgsNegateSubcommands)
         .arg(
             Arg::with_name("input")
                 .help("A pipeline scripe file to execute")
                 .required(true)
                 .index(1),
         )
+        .arg(
+            Arg::with_name("dry-run")
+                .long("dry-run")
+                .help("Prints the pipelines that would be executed instead of executing them"),
+        )
+        .arg(
+            Arg::with_name("watch")
+                .long("watch")
+                .help("Executes the pipeline again whenever its files or files it globbed change"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .value_name("MODE")
+                .possible_values(&["inherit", "prefixed", "buffered"])
+                .help(
+                    "How the output of executed commands is shown; prefixed prefixes every line \
+                     with its nonblock name or command, buffered also holds it until the command \
+                     exits",
+                ),
+        )
+        .arg(
+            Arg::with_name("color")
+                .long("color")
+                .help("Colors the output prefix of every nonblock pipeline differently"),
+        )
+        .arg(
+            Arg::with_name("log-file")
+                .long("log-file")
+                .value_name("PATH")
+                .help("Writes an event for every pipeline started and finished to the given file"),
+        )
+        .arg(
+            Arg::with_name("log-format")
+                .long("log-format")
+                .value_name("FORMAT")
+                .possible_values(&["text", "json"])
+                .requires("log-file")
+                .help(
+                    "The format of the log file, text by default; json writes an object per line",
+                ),
+        )
+        .arg(
+            Arg::with_name("trace")
+                .long("trace")
+                .value_name("PATH")
+                .help("Writes a trace of all pipeline executions in the Chrome Trace Event format"),
+        )
+        .arg(
+            Arg::with_name("no-cache").long("no-cache").help(
+                "Always runs exec pipelines instead of restoring their outputs from the cache",
+            ),
+        )
+        .arg(
+            Arg::with_name("remote-cache")
+                .long("remote-cache")
+                .value_name("URL")
+                .env("PIPED_REMOTE_CACHE")
+                .help("Shares the outputs of exec pipelines through the given HTTP cache server"),
+        )
+        .arg(
+            Arg::with_name("remote-cache-read-only")
+                .long("remote-cache-read-only")
+                .help("Only downloads from the remote cache, never uploads to it"),
+        )
+        .arg(
+            Arg::with_name("summary")
+                .long("summary")
+                .help("Prints every pipeline execution sorted by duration at the end of the run"),
+        )
+        .subcommand(
+            SubCommand::with_name("check")
+                .about("Checks a pipeline file and all its imports without executing them")
+                .arg(
+                    Arg::with_name("input")
+                        .help("A pipeline scripe file to check")
+                        .required(true)
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("fmt")
+                .about("Formats pipeline files in place")
+                .arg(
+                    Arg::with_name("check")
+                        .long("check")
+                        .help("Lists files that are not formatted instead of formatting them"),
+                )
+                .arg(
+                    Arg::with_name("input")
+                        .help("Pipeline scripe files to format")
+                        .required(true)
+                        .multiple(true)
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("test")
+                .about(
+                    "Runs the @test blocks and *.test.piped files found in the given files and \
+                     directories",
+                )
+                .arg(
+                    Arg::with_name("junit")
+                        .long("junit")
+                        .value_name("PATH")
+                        .help("Also writes the results to the given file in the JUnit XML format"),
+                )
+                .arg(
+                    Arg::with_name("input")
+                        .help(
+                            "Pipeline scripe files or directories to test; the current by default",
+                        )
+                        .multiple(true)
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("fetch")
+                .about(
+                    "Vendors the dependencies declared in piped.toml into .piped/deps and locks \
+                     their hashes",
+                )
+                .arg(
+                    Arg::with_name("update")
+                        .long("update")
+                        .help("Accepts dependencies whose content changed since they were locked"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("cache")
+                .about("Inspects or cleans up the cache of exec outputs")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("stats")
+                        .about("Prints the number and size of the cached actions and outputs"),
+                )
+                .subcommand(
+                    SubCommand::with_name("gc")
+                        .about("Removes actions not used recently and outputs no action refers to")
+                        .arg(
+                            Arg::with_name("max-age")
+                                .long("max-age")
+                                .value_name("DAYS")
+                                .default_value("30")
+                                .help("Removes actions not used within the given number of days"),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("ast")
+                .about("Prints the parsed syntax tree of a pipeline file as JSON")
+                .arg(
+                    Arg::with_name("input")
+                        .help("A pipeline scripe file to parse")
+                        .required(true)
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("repl")
+                .about("Executes statements and evaluates expressions entered interactively"),
+        )
+        .subcommand(
+            SubCommand::with_name("lsp")
+                .about("Starts a language server for pipeline files on the standard I/O"),
+        )
         .get_matches();
 
+    match matches.subcommand() {
+        ("check", Some(matches)) => {
+            check(matches.value_of("input").unwrap());
+            return;
+        }
+        ("fmt", Some(matches)) => {
+            fmt(
+                matches.values_of("input").unwrap().collect(),
+                matches.is_present("check"),
+            );
+            return;
+        }
+        ("test", Some(matches)) => {
+            let input_vec = match matches.values_of("input") {
+                Some(input_vec) => input_vec.map(PathBuf::from).collect(),
+                None => vec![PathBuf::from(".")],
+            };
+
+            if !run_tests(&input_vec, matches.value_of("junit").map(Path::new)) {
+                exit(1);
+            }
+            return;
+        }
+        ("fetch", Some(matches)) => {
+            fetch_dependencies(matches.is_present("update"));
+            return;
+        }
+        ("cache", Some(matches)) => {
+            match matches.subcommand() {
+                ("stats", Some(..)) => cache_stats(),
+                ("gc", Some(matches)) => {
+                    let max_age = match matches.value_of("max-age").unwrap().parse::<u64>() {
+                        Ok(max_age) => max_age,
+                        Err(err) => {
+                            eprintln!(
+                                "Invalid --max-age: {}",
+                                matches.value_of("max-age").unwrap()
+                            );
+                            eprintln!("\tbecause: {}", err);
+                            exit(1);
+                        }
+                    };
+
+                    cache_gc(Duration::from_secs(max_age * 24 * 60 * 60));
+                }
+                _ => unreachable!(),
+            }
+            return;
+        }
+        ("ast", Some(matches)) => {
+            ast(matches.value_of("input").unwrap());
+            return;
+        }
+        ("repl", Some(..)) => {
+            repl(Execution::new());
+            return;
+        }
+        ("lsp", Some(..)) => {
+            if let Err(err) = lsp::server::run() {
+                eprintln!("The language server stopped unexpectedly: {}", err);
+                exit(1);
+            }
+            return;
+        }
+        _ => {}
+    }
+
     let input = matches.values_of("input").unwrap().last().unwrap();
+
+    set_output_options(
+        OutputMode::from_name(matches.value_of("output").unwrap_or("inherit")).unwrap(),
+        matches.is_present("color"),
+    );
+    set_cache_enabled(!matches.is_present("no-cache"));
+    set_remote_cache(
+        matches.value_of("remote-cache"),
+        matches.is_present("remote-cache-read-only")
+            || var("PIPED_REMOTE_CACHE_READ_ONLY").is_ok_and(|value| value == "1"),
+    );
+
+    handle_signals();
+
+    if matches.is_present("watch") {
+        watch(Path::new(input), || build_execution(&matches));
+    }
+
     let entry_pipeline = match ImportedPipeline::import(&PathBuf::from(input)) {
         Ok(pipeline) => pipeline,
+        Err(err @ ImportError::Malformed(..)) => {
+            eprintln!("{}", err);
+            exit(-1);
+        }
         Err(err) => {
             eprintln!("Unable to read the given path: {}", input);
             eprintln!("\tbecause: {}", err);
@@ -30,5 +307,216 @@ fn main() {
         }
     };
 
-    Execution::new().execute(entry_pipeline);
+    build_execution(&matches).execute(entry_pipeline);
+    park_if_interrupted();
+}
+
+fn build_execution(matches: &ArgMatches) -> Execution {
+    let mut execution = Execution::new();
+    execution.set_dry_run(matches.is_present("dry-run"));
+    execution.set_summary(matches.is_present("summary"));
+
+    if let Some(log_file) = matches.value_of("log-file") {
+        match File::create(log_file) {
+            Ok(file) => execution.set_log_file(
+                file,
+                LogFormat::from_name(matches.value_of("log-format").unwrap_or("text")).unwrap(),
+            ),
+            Err(err) => {
+                eprintln!("Unable to create the log file: {}", log_file);
+                eprintln!("\tbecause: {}", err);
+                exit(-1);
+            }
+        }
+    }
+
+    if let Some(trace) = matches.value_of("trace") {
+        match File::create(trace) {
+            Ok(file) => execution.set_trace_file(file),
+            Err(err) => {
+                eprintln!("Unable to create the trace file: {}", trace);
+                eprintln!("\tbecause: {}", err);
+                exit(-1);
+            }
+        }
+    }
+
+    execution
+}
+
+fn check(input: &str) {
+    let mut checker = Checker::new();
+
+    match checker.check_entry(&PathBuf::from(input)) {
+        Ok(()) => {}
+        Err(err @ ImportError::Malformed(..)) => {
+            eprintln!("{}", err);
+            exit(1);
+        }
+        Err(err) => {
+            eprintln!("Unable to read the given path: {}", input);
+            eprintln!("\tbecause: {}", err);
+            exit(1);
+        }
+    }
+
+    for diagnostic in checker.diagnostic_vec().iter() {
+        eprintln!(
+            "{}",
+            diagnostic.render(checker.src_content(&diagnostic.file_path))
+        );
+    }
+
+    match checker.diagnostic_vec().len() {
+        0 => println!("No problems found."),
+        count => {
+            eprintln!(
+                "Found {} problem{}.",
+                count,
+                if count == 1 { "" } else { "s" }
+            );
+            exit(1);
+        }
+    }
+}
+
+fn fmt(input_vec: Vec<&str>, check_only: bool) {
+    let mut failed = false;
+
+    for input in input_vec {
+        let content = match read_to_string(input) {
+            Ok(content) => content,
+            Err(err) => {
+                eprintln!("Unable to read the given path: {}", input);
+                eprintln!("\tbecause: {}", err);
+                failed = true;
+                continue;
+            }
+        };
+        let formatted = match format(input, &content) {
+            Ok(formatted) => formatted,
+            Err(diagnostic_vec) => {
+                let src_content = content
+                    .replace("\r\n", "\n")
+                    .split_terminator('\n')
+                    .map(|line| line.to_owned())
+                    .collect();
+                eprintln!("{}", ImportError::Malformed(diagnostic_vec, src_content));
+                failed = true;
+                continue;
+            }
+        };
+
+        if formatted == content {
+            continue;
+        }
+
+        if check_only {
+            println!("{}", input);
+            failed = true;
+        } else if let Err(err) = write(input, formatted) {
+            eprintln!("Unable to write the given path: {}", input);
+            eprintln!("\tbecause: {}", err);
+            failed = true;
+        }
+    }
+
+    if failed {
+        exit(1);
+    }
+}
+
+fn fetch_dependencies(update: bool) {
+    let project_config = match current_dir()
+        .map_err(|err| err.to_string())
+        .and_then(|directory| ProjectConfig::find(&directory))
+    {
+        Ok(Some(project_config)) => project_config,
+        Ok(None) => {
+            eprintln!(
+                "Unable to find a {} in the current directory or its ancestors.",
+                PROJECT_CONFIG_FILE_NAME
+            );
+            exit(1);
+        }
+        Err(err) => {
+            eprintln!("Unable to load the project config.");
+            eprintln!("\tbecause: {}", err);
+            exit(1);
+        }
+    };
+
+    if let Err(err) = fetch(&project_config, update) {
+        eprintln!("Unable to fetch the dependencies.");
+        eprintln!("\tbecause: {}", err);
+        exit(1);
+    }
+}
+
+fn cache_stats() {
+    let cache = ActionCache::open();
+
+    match cache.stats() {
+        Ok(stats) => {
+            println!("Cache directory: {}", cache.directory().display());
+            println!("Actions: {}", stats.action_count);
+            println!(
+                "Outputs: {} ({})",
+                stats.blob_count,
+                format_size(stats.blob_size)
+            );
+        }
+        Err(err) => {
+            eprintln!("Unable to read the cache: {}", cache.directory().display());
+            eprintln!("\tbecause: {}", err);
+            exit(1);
+        }
+    }
+}
+
+fn cache_gc(max_age: Duration) {
+    let cache = ActionCache::open();
+
+    match cache.gc(max_age) {
+        Ok(removed) => println!(
+            "Removed {} action{} and {} output{} ({}).",
+            removed.action_count,
+            if removed.action_count == 1 { "" } else { "s" },
+            removed.blob_count,
+            if removed.blob_count == 1 { "" } else { "s" },
+            format_size(removed.blob_size)
+        ),
+        Err(err) => {
+            eprintln!(
+                "Unable to clean up the cache: {}",
+                cache.directory().display()
+            );
+            eprintln!("\tbecause: {}", err);
+            exit(1);
+        }
+    }
+}
+
+fn ast(input: &str) {
+    let content = match read_to_string(input) {
+        Ok(content) => content,
+        Err(err) => {
+            eprintln!("Unable to read the given path: {}", input);
+            eprintln!("\tbecause: {}", err);
+            exit(1);
+        }
+    };
+
+    match dump(input, &content) {
+        Ok(json) => println!("{}", json),
+        Err(diagnostic_vec) => {
+            let src_content = content
+                .replace("\r\n", "\n")
+                .split_terminator('\n')
+                .map(|line| line.to_owned())
+                .collect();
+            eprintln!("{}", ImportError::Malformed(diagnostic_vec, src_content));
+            exit(1);
+        }
+    }
 }
@@ -0,0 +1,183 @@
+use super::super::runtime::project_config::{DependencySource, ProjectConfig};
+use super::lockfile::{LockedPackage, Lockfile, LOCKFILE_NAME};
+use sha2::{Digest, Sha256};
+use std::fs::{copy, create_dir_all, read, read_dir, remove_dir_all};
+use std::path::{Path, PathBuf};
+
+struct ResolvedPackage {
+	directory: PathBuf,
+	// Paths of the files of the package, relative to its directory.
+	file_vec: Vec<PathBuf>,
+	locked: LockedPackage,
+}
+
+// Vendors the dependencies of the project into its dependency directory and records their
+// content hashes in the lockfile. Unless updating, a dependency whose content no longer matches
+// its locked hash is an error and nothing is vendored.
+pub fn fetch(project_config: &ProjectConfig, update: bool) -> Result<(), String> {
+	let lockfile_path = project_config.directory().join(LOCKFILE_NAME);
+	let lockfile = Lockfile::load(&lockfile_path)?;
+	let mut package_vec = Vec::new();
+
+	for dependency in project_config.dependency_vec().iter() {
+		let directory = dependency.source.directory(&dependency.name)?;
+
+		if !directory.is_dir() {
+			return Err(format!(
+				"the package '{}' does not exist at '{}'",
+				dependency.name,
+				directory.display()
+			));
+		}
+
+		let mut file_vec = Vec::new();
+		collect_files(&directory, Path::new(""), &mut file_vec)?;
+
+		let (source, version) = match &dependency.source {
+			DependencySource::Registry { version, .. } => ("registry", Some(version.clone())),
+			DependencySource::Path(..) => ("path", None),
+		};
+		let locked = LockedPackage {
+			name: dependency.name.clone(),
+			version,
+			source: source.to_owned(),
+			hash: hash_package(&directory, &file_vec)?,
+		};
+
+		if let Some(previous) = lockfile.get(&dependency.name) {
+			if !update
+				&& previous.source == locked.source
+				&& previous.version == locked.version
+				&& previous.hash != locked.hash
+			{
+				return Err(format!(
+					"the content of '{}' at '{}' does not match its hash in {}; run piped fetch --update if the change is expected",
+					dependency.name,
+					directory.display(),
+					LOCKFILE_NAME
+				));
+			}
+		}
+
+		package_vec.push(ResolvedPackage {
+			directory,
+			file_vec,
+			locked,
+		});
+	}
+
+	let dependency_directory = project_config.dependency_directory();
+	let mut next_lockfile = Lockfile::new();
+
+	// Vendored packages that are no longer declared are removed.
+	if let Ok(entries) = read_dir(&dependency_directory) {
+		for entry in entries.flatten() {
+			let name = entry.file_name().to_string_lossy().into_owned();
+
+			if !package_vec
+				.iter()
+				.any(|package| package.locked.name == name)
+			{
+				remove_dir_all(entry.path()).map_err(|err| {
+					format!("unable to remove '{}': {}", entry.path().display(), err)
+				})?;
+			}
+		}
+	}
+
+	for package in package_vec {
+		let target_directory = dependency_directory.join(&package.locked.name);
+
+		if target_directory.exists() {
+			remove_dir_all(&target_directory).map_err(|err| {
+				format!("unable to remove '{}': {}", target_directory.display(), err)
+			})?;
+		}
+
+		for file in package.file_vec.iter() {
+			let target = target_directory.join(file);
+
+			if let Some(parent) = target.parent() {
+				create_dir_all(parent)
+					.map_err(|err| format!("unable to create '{}': {}", parent.display(), err))?;
+			}
+
+			copy(package.directory.join(file), &target)
+				.map_err(|err| format!("unable to copy to '{}': {}", target.display(), err))?;
+		}
+
+		println!(
+			"Fetched {}{} from {}",
+			package.locked.name,
+			package
+				.locked
+				.version
+				.as_ref()
+				.map(|version| format!(" {}", version))
+				.unwrap_or_default(),
+			package.directory.display()
+		);
+
+		next_lockfile.insert(package.locked);
+	}
+
+	next_lockfile.save(&lockfile_path)
+}
+
+// Collects the files of a package in a stable order. Hidden files and directories, such as .git
+// or the dependencies vendored by the package itself, are not part of it.
+fn collect_files(root: &Path, relative: &Path, file_vec: &mut Vec<PathBuf>) -> Result<(), String> {
+	let directory = root.join(relative);
+	let mut entry_vec = read_dir(&directory)
+		.map_err(|err| format!("unable to read '{}': {}", directory.display(), err))?
+		.flatten()
+		.collect::<Vec<_>>();
+	entry_vec.sort_by_key(|entry| entry.file_name());
+
+	for entry in entry_vec {
+		let file_name = entry.file_name();
+
+		if file_name.to_string_lossy().starts_with('.') {
+			continue;
+		}
+
+		let path = relative.join(&file_name);
+
+		if entry.path().is_dir() {
+			collect_files(root, &path, file_vec)?;
+		} else {
+			file_vec.push(path);
+		}
+	}
+
+	Ok(())
+}
+
+// Hashes the paths and contents of the given files, so that renaming a file changes the hash too.
+fn hash_package(directory: &Path, file_vec: &[PathBuf]) -> Result<String, String> {
+	let mut hasher = Sha256::new();
+
+	for file in file_vec.iter() {
+		let content = read(directory.join(file))
+			.map_err(|err| format!("unable to read '{}': {}", file.display(), err))?;
+		let path = file
+			.components()
+			.map(|component| component.as_os_str().to_string_lossy())
+			.collect::<Vec<_>>()
+			.join("/");
+
+		hasher.update(path.as_bytes());
+		hasher.update([0]);
+		hasher.update((content.len() as u64).to_le_bytes());
+		hasher.update(&content);
+	}
+
+	Ok(format!(
+		"sha256:{}",
+		hasher
+			.finalize()
+			.iter()
+			.map(|byte| format!("{:02x}", byte))
+			.collect::<String>()
+	))
+}
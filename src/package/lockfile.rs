@@ -0,0 +1,141 @@
+use std::fs::{read_to_string, write};
+use std::path::Path;
+use toml::Value;
+
+pub const LOCKFILE_NAME: &str = "piped.lock";
+
+#[derive(Clone, PartialEq)]
+pub struct LockedPackage {
+	pub name: String,
+	// The registry version; None for path dependencies.
+	pub version: Option<String>,
+	// Either "registry" or "path". Locations are not recorded, as they differ between machines.
+	pub source: String,
+	// The hash of the vendored files, see hash_package.
+	pub hash: String,
+}
+
+// The piped.lock next to the piped.toml, written by piped fetch.
+pub struct Lockfile {
+	package_vec: Vec<LockedPackage>,
+}
+
+impl Lockfile {
+	pub fn new() -> Lockfile {
+		Lockfile {
+			package_vec: Vec::new(),
+		}
+	}
+
+	// Loads the lockfile at the given path; a missing lockfile is an empty one.
+	pub fn load(path: &Path) -> Result<Lockfile, String> {
+		if !path.exists() {
+			return Ok(Lockfile::new());
+		}
+
+		let invalid = |reason: String| format!("invalid lockfile '{}': {}", path.display(), reason);
+		let content = read_to_string(path).map_err(|err| invalid(err.to_string()))?;
+		let lockfile = content
+			.parse::<Value>()
+			.map_err(|err| invalid(err.to_string()))?;
+		let mut package_vec = Vec::new();
+
+		if let Some(packages) = lockfile.get("package") {
+			for package in packages
+				.as_array()
+				.ok_or_else(|| invalid("'package' must be an array of tables".to_owned()))?
+			{
+				let field = |name: &str| package.get(name).and_then(|value| value.as_str());
+
+				package_vec.push(LockedPackage {
+					name: field("name")
+						.ok_or_else(|| invalid("a package has no name".to_owned()))?
+						.to_owned(),
+					version: field("version").map(|version| version.to_owned()),
+					source: field("source")
+						.ok_or_else(|| invalid("a package has no source".to_owned()))?
+						.to_owned(),
+					hash: field("hash")
+						.ok_or_else(|| invalid("a package has no hash".to_owned()))?
+						.to_owned(),
+				});
+			}
+		}
+
+		Ok(Lockfile { package_vec })
+	}
+
+	pub fn get(&self, name: &str) -> Option<&LockedPackage> {
+		self.package_vec.iter().find(|package| package.name == name)
+	}
+
+	pub fn insert(&mut self, package: LockedPackage) {
+		self.package_vec
+			.retain(|locked| locked.name != package.name);
+		self.package_vec.push(package);
+		self.package_vec.sort_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
+	}
+
+	pub fn save(&self, path: &Path) -> Result<(), String> {
+		let mut content = "# Generated by piped fetch; do not edit it by hand.\n".to_owned();
+
+		for package in self.package_vec.iter() {
+			content += "\n[[package]]\n";
+			content += &format!("name = {}\n", quote(&package.name));
+
+			if let Some(version) = &package.version {
+				content += &format!("version = {}\n", quote(version));
+			}
+
+			content += &format!("source = {}\n", quote(&package.source));
+			content += &format!("hash = {}\n", quote(&package.hash));
+		}
+
+		write(path, content)
+			.map_err(|err| format!("unable to write the lockfile '{}': {}", path.display(), err))
+	}
+}
+
+fn quote(value: &str) -> String {
+	Value::String(value.to_owned()).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::fs::remove_file;
+	use std::process;
+
+	#[test]
+	fn loads_what_it_saved() {
+		let path = std::env::temp_dir().join(format!("piped-{}.lock", process::id()));
+		let mut lockfile = Lockfile::new();
+		lockfile.insert(LockedPackage {
+			name: "shared".to_owned(),
+			version: None,
+			source: "path".to_owned(),
+			hash: "sha256:01".to_owned(),
+		});
+		lockfile.insert(LockedPackage {
+			name: "deploy \"prod\"".to_owned(),
+			version: Some("0.3.0".to_owned()),
+			source: "registry".to_owned(),
+			hash: "sha256:02".to_owned(),
+		});
+		lockfile.save(&path).unwrap();
+
+		let loaded = Lockfile::load(&path);
+		remove_file(&path).unwrap();
+		let loaded = loaded.unwrap();
+
+		assert!(loaded.package_vec == lockfile.package_vec);
+		assert!(loaded.get("deploy \"prod\"").unwrap().version == Some("0.3.0".to_owned()));
+	}
+
+	#[test]
+	fn loads_a_missing_lockfile_as_an_empty_one() {
+		let path = std::env::temp_dir().join(format!("piped-missing-{}.lock", process::id()));
+
+		assert!(Lockfile::load(&path).unwrap().package_vec.is_empty());
+	}
+}
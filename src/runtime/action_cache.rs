@@ -0,0 +1,685 @@
+use super::project_config::PROJECT_CONFIG_FILE_NAME;
+use super::remote_cache::remote_cache;
+use glob::glob;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::env::{current_dir, split_paths, var_os};
+use std::fs::{
+	copy, create_dir_all, metadata, read, read_dir, read_to_string, remove_file, rename, write,
+	File, Metadata,
+};
+use std::path::{Component, Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+
+// Bump this whenever the way keys are computed or entries are stored changes, so that entries of
+// older versions are never restored.
+const CACHE_VERSION: u64 = 2;
+
+// Pipelines have no access to the execution, so whether the cache is used is process-wide.
+static CACHE_ENABLED: AtomicBool = AtomicBool::new(true);
+
+pub fn set_cache_enabled(enabled: bool) {
+	CACHE_ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+pub fn is_cache_enabled() -> bool {
+	CACHE_ENABLED.load(Ordering::SeqCst)
+}
+
+// Everything an exec pipeline depends on. Actions with the same key are expected to produce the
+// same outputs.
+pub struct Action<'a> {
+	pub cmd: &'a str,
+	pub params: &'a [String],
+	pub envs: &'a HashMap<String, String>,
+	pub input_vec: &'a [String],
+	pub output_vec: &'a [String],
+}
+
+#[derive(Default)]
+pub struct CacheStats {
+	pub action_count: usize,
+	pub blob_count: usize,
+	pub blob_size: u64,
+}
+
+// A content-addressed store of action outputs. Blobs are stored by the hash of their content in
+// blobs/, and every cached action has a manifest in actions/ that maps its outputs to blobs.
+pub struct ActionCache {
+	directory: PathBuf,
+}
+
+impl ActionCache {
+	// The cache lives in PIPED_CACHE_DIR if set, or in the user cache directory otherwise.
+	pub fn open() -> ActionCache {
+		let directory = match var_os("PIPED_CACHE_DIR") {
+			Some(directory) => PathBuf::from(directory),
+			None => match var_os("XDG_CACHE_HOME") {
+				Some(directory) => PathBuf::from(directory).join("piped"),
+				None => match var_os("HOME").or_else(|| var_os("USERPROFILE")) {
+					Some(home) => PathBuf::from(home).join(".cache").join("piped"),
+					None => PathBuf::from(".piped").join("cache"),
+				},
+			},
+		};
+
+		ActionCache { directory }
+	}
+
+	pub fn directory(&self) -> &PathBuf {
+		&self.directory
+	}
+
+	// Computes the key of the action from its command, arguments, environment variables, working
+	// directory, the content of its inputs and the binary of its command.
+	pub fn action_key(&self, action: &Action) -> Result<String, String> {
+		let mut env_vec = action.envs.iter().collect::<Vec<_>>();
+		env_vec.sort();
+
+		let mut input_vec = Vec::new();
+
+		for file in collect_inputs(action.input_vec)? {
+			let content = read(&file)
+				.map_err(|err| format!("unable to read '{}': {}", file.display(), err))?;
+			input_vec.push(json!([file.to_string_lossy(), hash_content(&content)]));
+		}
+
+		let tool = match find_tool(action.cmd) {
+			Some(tool) => match read(&tool) {
+				Ok(content) => json!(hash_content(&content)),
+				Err(..) => Value::Null,
+			},
+			None => Value::Null,
+		};
+		let key = json!({
+			"version": CACHE_VERSION,
+			"cmd": action.cmd,
+			"tool": tool,
+			"params": action.params,
+			"envs": env_vec,
+			"cwd": working_directory()?,
+			"inputs": input_vec,
+			"outputs": action.output_vec,
+		});
+
+		Ok(hash_content(key.to_string().as_bytes()))
+	}
+
+	// Restores the outputs of the action with the given key, and returns whether it was cached.
+	// Actions not cached locally are looked up in the remote cache, if any. Only files under the
+	// given declared outputs are restored.
+	pub fn restore(&self, key: &str, output_vec: &[String]) -> Result<bool, String> {
+		let entry_vec = match self.local_entries(key, output_vec)? {
+			Some(entry_vec) => entry_vec,
+			None => match self.download(key, output_vec)? {
+				Some(entry_vec) => entry_vec,
+				None => return Ok(false),
+			},
+		};
+
+		for entry in entry_vec {
+			if let Some(parent) = entry.path.parent() {
+				create_dir_all(parent)
+					.map_err(|err| format!("unable to create '{}': {}", parent.display(), err))?;
+			}
+
+			// Replace instead of overwriting, so that hard links or running binaries are not
+			// modified in place.
+			let _ = remove_file(&entry.path);
+			copy(self.blob_path(&entry.hash), &entry.path)
+				.and_then(|_| set_executable(&entry.path, entry.executable))
+				.map_err(|err| format!("unable to restore '{}': {}", entry.path.display(), err))?;
+		}
+
+		// The modification time of a manifest is its last use; see gc.
+		if let Ok(file) = File::options().append(true).open(self.manifest_path(key)) {
+			let _ = file.set_modified(SystemTime::now());
+		}
+
+		Ok(true)
+	}
+
+	// Stores the declared outputs of the action with the given key. Directories are stored with
+	// all the files in them. The outputs are uploaded to the remote cache too, unless it is
+	// read-only.
+	pub fn store(&self, key: &str, output_vec: &[String]) -> Result<(), String> {
+		let mut file_vec = Vec::new();
+
+		for output in output_vec.iter() {
+			let path = PathBuf::from(output);
+
+			if !is_relative_inside(&path) {
+				return Err(format!(
+					"the declared output '{}' must be a relative path without '..'",
+					output
+				));
+			}
+
+			if path.is_dir() {
+				collect_files(&path, &mut file_vec)?;
+			} else if path.is_file() {
+				file_vec.push(path);
+			} else {
+				return Err(format!("the declared output '{}' was not produced", output));
+			}
+		}
+
+		let mut entry_vec = Vec::new();
+		let mut hash_vec = Vec::new();
+
+		for file in file_vec {
+			let content = read(&file)
+				.map_err(|err| format!("unable to read '{}': {}", file.display(), err))?;
+			let hash = hash_content(&content);
+			let blob_path = self.blob_path(&hash);
+
+			if !blob_path.is_file() {
+				write_atomically(&blob_path, &content)?;
+			}
+
+			let executable = metadata(&file)
+				.map(|metadata| is_executable(&metadata))
+				.unwrap_or(false);
+
+			entry_vec.push(json!({
+				"path": file.to_string_lossy(),
+				"hash": hash,
+				"executable": executable,
+			}));
+			hash_vec.push(hash);
+		}
+
+		let manifest = json!({ "outputs": entry_vec }).to_string();
+		write_atomically(&self.manifest_path(key), manifest.as_bytes())?;
+
+		if let Some(remote_cache) = remote_cache().filter(|remote| !remote.is_read_only()) {
+			// Blobs go first, so that other machines never see an action with missing outputs.
+			for hash in hash_vec {
+				let blob_path = self.blob_path(&hash);
+				let content = read(&blob_path)
+					.map_err(|err| format!("unable to read '{}': {}", blob_path.display(), err))?;
+				remote_cache.put_blob(&hash, &content)?;
+			}
+
+			remote_cache.put_action(key, manifest.as_bytes())?;
+		}
+
+		Ok(())
+	}
+
+	pub fn stats(&self) -> Result<CacheStats, String> {
+		let mut stats = CacheStats::default();
+
+		for path in self.list(&self.directory.join("actions"))? {
+			if !is_temporary(&path) {
+				stats.action_count += 1;
+			}
+		}
+
+		for path in self.list(&self.directory.join("blobs"))? {
+			if !is_temporary(&path) {
+				stats.blob_count += 1;
+				stats.blob_size += metadata(&path).map(|metadata| metadata.len()).unwrap_or(0);
+			}
+		}
+
+		Ok(stats)
+	}
+
+	// Removes the actions not used within the given duration, then the blobs no remaining action
+	// refers to, and returns what was removed.
+	pub fn gc(&self, max_age: Duration) -> Result<CacheStats, String> {
+		let mut removed = CacheStats::default();
+		let mut referenced_hash_set = HashSet::new();
+		let now = SystemTime::now();
+
+		for path in self.list(&self.directory.join("actions"))? {
+			let age = metadata(&path)
+				.and_then(|metadata| metadata.modified())
+				.ok()
+				.and_then(|modified| now.duration_since(modified).ok())
+				.unwrap_or_default();
+			let manifest = read_to_string(&path)
+				.ok()
+				.and_then(|content| serde_json::from_str::<Value>(&content).ok());
+
+			match manifest {
+				Some(manifest) if max_age >= age && !is_temporary(&path) => {
+					for output in manifest["outputs"].as_array().into_iter().flatten() {
+						if let Some(hash) = output["hash"].as_str() {
+							referenced_hash_set.insert(hash.to_owned());
+						}
+					}
+				}
+				_ => {
+					remove_file(&path)
+						.map_err(|err| format!("unable to remove '{}': {}", path.display(), err))?;
+
+					if !is_temporary(&path) {
+						removed.action_count += 1;
+					}
+				}
+			}
+		}
+
+		for path in self.list(&self.directory.join("blobs"))? {
+			let hash = path
+				.file_name()
+				.map(|file_name| file_name.to_string_lossy().into_owned())
+				.unwrap_or_default();
+
+			if referenced_hash_set.contains(&hash) {
+				continue;
+			}
+
+			let size = metadata(&path).map(|metadata| metadata.len()).unwrap_or(0);
+			remove_file(&path)
+				.map_err(|err| format!("unable to remove '{}': {}", path.display(), err))?;
+
+			if !is_temporary(&path) {
+				removed.blob_count += 1;
+				removed.blob_size += size;
+			}
+		}
+
+		Ok(removed)
+	}
+
+	// The outputs of the action if it is cached locally with all its blobs.
+	fn local_entries(
+		&self,
+		key: &str,
+		output_vec: &[String],
+	) -> Result<Option<Vec<OutputEntry>>, String> {
+		let manifest_path = self.manifest_path(key);
+
+		if !manifest_path.is_file() {
+			return Ok(None);
+		}
+
+		let entry_vec = read_to_string(&manifest_path)
+			.map_err(|err| err.to_string())
+			.and_then(|content| parse_manifest(&content, output_vec))
+			.map_err(|err| format!("malformed entry '{}': {}", manifest_path.display(), err))?;
+
+		// An entry whose blobs were removed behaves as if it were not cached at all.
+		if entry_vec
+			.iter()
+			.any(|entry| !self.blob_path(&entry.hash).is_file())
+		{
+			return Ok(None);
+		}
+
+		Ok(Some(entry_vec))
+	}
+
+	// Downloads the action and the blobs missing locally from the remote cache, if any, and
+	// stores them locally.
+	fn download(
+		&self,
+		key: &str,
+		output_vec: &[String],
+	) -> Result<Option<Vec<OutputEntry>>, String> {
+		let remote_cache = match remote_cache() {
+			Some(remote_cache) => remote_cache,
+			None => return Ok(None),
+		};
+		let manifest = match remote_cache.get_action(key)? {
+			Some(manifest) => manifest,
+			None => return Ok(None),
+		};
+		let entry_vec = String::from_utf8(manifest.clone())
+			.map_err(|err| err.to_string())
+			.and_then(|manifest| parse_manifest(&manifest, output_vec))
+			.map_err(|err| format!("malformed remote entry '{}': {}", key, err))?;
+
+		for entry in entry_vec.iter() {
+			let blob_path = self.blob_path(&entry.hash);
+
+			if blob_path.is_file() {
+				continue;
+			}
+
+			match remote_cache.get_blob(&entry.hash)? {
+				Some(content) => write_atomically(&blob_path, &content)?,
+				None => return Ok(None),
+			}
+		}
+
+		write_atomically(&self.manifest_path(key), &manifest)?;
+		Ok(Some(entry_vec))
+	}
+
+	fn manifest_path(&self, key: &str) -> PathBuf {
+		self.directory
+			.join("actions")
+			.join(&key[..2])
+			.join(format!("{}.json", key))
+	}
+
+	fn blob_path(&self, hash: &str) -> PathBuf {
+		self.directory.join("blobs").join(&hash[..2]).join(hash)
+	}
+
+	// Lists the files in the shards of the given directory of the cache.
+	fn list(&self, directory: &Path) -> Result<Vec<PathBuf>, String> {
+		let mut path_vec = Vec::new();
+
+		if !directory.is_dir() {
+			return Ok(path_vec);
+		}
+
+		collect_files(directory, &mut path_vec)?;
+		Ok(path_vec)
+	}
+}
+
+struct OutputEntry {
+	path: PathBuf,
+	hash: String,
+	executable: bool,
+}
+
+// Parses a manifest, local or downloaded. Paths are restored as they are, so every one of them
+// must be under one of the declared outputs of the action.
+fn parse_manifest(content: &str, output_vec: &[String]) -> Result<Vec<OutputEntry>, String> {
+	let manifest = serde_json::from_str::<Value>(content).map_err(|err| err.to_string())?;
+	let mut entry_vec = Vec::new();
+
+	for output in manifest["outputs"].as_array().into_iter().flatten() {
+		match (
+			output["path"].as_str(),
+			output["hash"].as_str(),
+			output["executable"].as_bool(),
+		) {
+			// The hash names a file of the cache; anything but a hash could escape it.
+			(Some(path), Some(hash), Some(executable))
+				if hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()) =>
+			{
+				entry_vec.push(OutputEntry {
+					path: PathBuf::from(path),
+					hash: hash.to_owned(),
+					executable,
+				})
+			}
+			_ => return Err("invalid output".to_owned()),
+		}
+	}
+
+	for entry in entry_vec.iter() {
+		if !is_relative_inside(&entry.path)
+			|| !output_vec
+				.iter()
+				.any(|output| entry.path.starts_with(output))
+		{
+			return Err(format!(
+				"the output '{}' is not under a declared output",
+				entry.path.display()
+			));
+		}
+	}
+
+	Ok(entry_vec)
+}
+
+// Whether the path is relative and stays in the directory it is relative to.
+fn is_relative_inside(path: &Path) -> bool {
+	path.components().next().is_some()
+		&& path
+			.components()
+			.all(|component| matches!(component, Component::Normal(..) | Component::CurDir))
+}
+
+pub fn format_size(size: u64) -> String {
+	let mut size = size as f64;
+
+	for unit in ["B", "KiB", "MiB", "GiB"] {
+		if size < 1024.0 {
+			return if unit == "B" {
+				format!("{} {}", size, unit)
+			} else {
+				format!("{:.1} {}", size, unit)
+			};
+		}
+
+		size /= 1024.0;
+	}
+
+	format!("{:.1} TiB", size)
+}
+
+// Expands the declared inputs, which may be files, directories or glob patterns, into a sorted
+// list of files.
+fn collect_inputs(input_vec: &[String]) -> Result<Vec<PathBuf>, String> {
+	let mut file_vec = Vec::new();
+
+	for input in input_vec.iter() {
+		let path_vec = glob(input)
+			.map_err(|err| format!("invalid input pattern '{}': {}", input, err))?
+			.flatten()
+			.collect::<Vec<_>>();
+
+		if path_vec.is_empty() {
+			return Err(format!("the declared input '{}' does not exist", input));
+		}
+
+		for path in path_vec {
+			if path.is_dir() {
+				collect_files(&path, &mut file_vec)?;
+			} else {
+				file_vec.push(path);
+			}
+		}
+	}
+
+	file_vec.sort();
+	file_vec.dedup();
+	Ok(file_vec)
+}
+
+fn collect_files(directory: &Path, file_vec: &mut Vec<PathBuf>) -> Result<(), String> {
+	let mut path_vec = read_dir(directory)
+		.map_err(|err| format!("unable to read '{}': {}", directory.display(), err))?
+		.flatten()
+		.map(|entry| entry.path())
+		.collect::<Vec<_>>();
+	path_vec.sort();
+
+	for path in path_vec {
+		if path.is_dir() {
+			collect_files(&path, file_vec)?;
+		} else {
+			file_vec.push(path);
+		}
+	}
+
+	Ok(())
+}
+
+// The working directory relative to the root of the project if any, so that checkouts at
+// different paths, e.g. on CI and developer machines, share their keys.
+fn working_directory() -> Result<String, String> {
+	let working_directory = current_dir().map_err(|err| err.to_string())?;
+
+	for directory in working_directory.ancestors() {
+		if directory.join(PROJECT_CONFIG_FILE_NAME).is_file() {
+			return Ok(working_directory
+				.strip_prefix(directory)
+				.unwrap()
+				.components()
+				.map(|component| component.as_os_str().to_string_lossy())
+				.collect::<Vec<_>>()
+				.join("/"));
+		}
+	}
+
+	Ok(working_directory.to_string_lossy().into_owned())
+}
+
+// Finds the binary a command runs, the same way the command is spawned.
+fn find_tool(cmd: &str) -> Option<PathBuf> {
+	let path = Path::new(cmd);
+
+	if path.components().count() > 1 {
+		return Some(path.to_path_buf()).filter(|path| path.is_file());
+	}
+
+	split_paths(&var_os("PATH")?).find_map(|directory| {
+		let candidate = directory.join(cmd);
+
+		if candidate.is_file() {
+			return Some(candidate);
+		}
+
+		let candidate = directory.join(format!("{}.exe", cmd));
+		Some(candidate).filter(|candidate| candidate.is_file())
+	})
+}
+
+fn hash_content(content: &[u8]) -> String {
+	Sha256::digest(content)
+		.iter()
+		.map(|byte| format!("{:02x}", byte))
+		.collect()
+}
+
+// Writes through a temporary file, so that concurrent runs never see a partially written entry.
+fn write_atomically(path: &Path, content: &[u8]) -> Result<(), String> {
+	let parent = path.parent().unwrap();
+	create_dir_all(parent)
+		.map_err(|err| format!("unable to create '{}': {}", parent.display(), err))?;
+
+	let temporary_path = parent.join(format!(
+		".tmp-{}-{}",
+		process::id(),
+		path.file_name().unwrap().to_string_lossy()
+	));
+
+	write(&temporary_path, content)
+		.and_then(|_| rename(&temporary_path, path))
+		.map_err(|err| {
+			let _ = remove_file(&temporary_path);
+			format!("unable to write '{}': {}", path.display(), err)
+		})
+}
+
+// Temporary files left behind by interrupted writes.
+fn is_temporary(path: &Path) -> bool {
+	path.file_name()
+		.map(|file_name| file_name.to_string_lossy().starts_with(".tmp-"))
+		.unwrap_or(false)
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &Metadata) -> bool {
+	use std::os::unix::fs::PermissionsExt;
+
+	metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &Metadata) -> bool {
+	false
+}
+
+#[cfg(unix)]
+fn set_executable(path: &Path, executable: bool) -> std::io::Result<()> {
+	use std::fs::{set_permissions, Permissions};
+	use std::os::unix::fs::PermissionsExt;
+
+	set_permissions(
+		path,
+		Permissions::from_mode(if executable { 0o755 } else { 0o644 }),
+	)
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &Path, _executable: bool) -> std::io::Result<()> {
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn manifest(path: &str) -> String {
+		json!({
+			"outputs": [{ "path": path, "hash": "ab".repeat(32), "executable": false }],
+		})
+		.to_string()
+	}
+
+	fn outputs() -> Vec<String> {
+		vec!["out/hello".to_owned(), "./dist".to_owned()]
+	}
+
+	#[test]
+	fn parses_outputs_under_the_declared_ones() {
+		for path in ["out/hello", "./dist/app/main.js"] {
+			let entry_vec = parse_manifest(&manifest(path), &outputs()).unwrap();
+
+			assert_eq!(entry_vec.len(), 1);
+			assert_eq!(entry_vec[0].path, PathBuf::from(path));
+			assert!(!entry_vec[0].executable);
+		}
+	}
+
+	#[test]
+	fn rejects_outputs_that_could_escape() {
+		for path in [
+			"/etc/passwd",
+			"out/hello/../../etc/passwd",
+			"../out/hello",
+			"out/hello2",
+			"out",
+			"",
+		] {
+			assert!(
+				parse_manifest(&manifest(path), &outputs()).is_err(),
+				"{}",
+				path
+			);
+		}
+	}
+
+	#[test]
+	fn rejects_malformed_outputs() {
+		let content = json!({
+			"outputs": [{ "path": "out/hello", "hash": "../../bin/sh", "executable": false }],
+		})
+		.to_string();
+
+		assert!(parse_manifest(&content, &outputs()).is_err());
+		assert!(parse_manifest("{", &outputs()).is_err());
+	}
+
+	#[test]
+	fn collects_only_the_blobs_no_action_refers_to() {
+		let cache = ActionCache {
+			directory: std::env::temp_dir().join(format!("piped-cache-gc-{}", std::process::id())),
+		};
+		let referenced_hash = "ab".repeat(32);
+		let unreferenced_hash = "cd".repeat(32);
+
+		write_atomically(
+			&cache.manifest_path("action"),
+			manifest("out/hello").as_bytes(),
+		)
+		.unwrap();
+		write_atomically(&cache.blob_path(&referenced_hash), b"hello").unwrap();
+		write_atomically(&cache.blob_path(&unreferenced_hash), b"bye").unwrap();
+
+		let removed = cache.gc(Duration::from_secs(3600));
+		let referenced_exists = cache.blob_path(&referenced_hash).is_file();
+		let unreferenced_exists = cache.blob_path(&unreferenced_hash).is_file();
+		std::fs::remove_dir_all(cache.directory()).unwrap();
+
+		let removed = removed.unwrap();
+		assert_eq!((removed.action_count, removed.blob_count), (0, 1));
+		assert!(referenced_exists);
+		assert!(!unreferenced_exists);
+	}
+}
@@ -1,12 +1,19 @@
 use super::super::super::function::Function;
 use super::{
-    function_contains, function_equals, function_get, function_glob, function_is_exists,
-    function_join_path, function_len, function_re_replace, function_typeof,
+    function_contains, function_equals, function_get, function_glob, function_hash_file,
+    function_hash_files, function_hash_string, function_is_exists, function_join_path,
+    function_len, function_re_replace, function_typeof,
 };
 use std::collections::HashMap;
 
 macro_rules! define_function {
-    ($name:ident ($execution:ident, $arg:ident) => $body:block) => {
+    ($name:ident [..] ($execution:ident, $arg:ident) => $body:block) => {
+        define_function!(@define $name, None, ($execution, $arg) => $body);
+    };
+    ($name:ident [$argument_count:literal] ($execution:ident, $arg:ident) => $body:block) => {
+        define_function!(@define $name, Some($argument_count), ($execution, $arg) => $body);
+    };
+    (@define $name:ident, $argument_count:expr, ($execution:ident, $arg:ident) => $body:block) => {
         use super::super::super::function::Function;
         use super::super::super::sub_execution::SubExecution;
         use super::super::super::value::Value;
@@ -20,6 +27,10 @@ macro_rules! define_function {
         }
 
         impl Function for $name {
+            fn argument_count(&self) -> Option<usize> {
+                $argument_count
+            }
+
             fn call(&self, $execution: &mut SubExecution, $arg: Vec<Value>) -> Value $body
         }
     };
@@ -38,6 +49,9 @@ pub fn build_function_map() -> HashMap<String, Box<dyn Function + Send + Sync>>
     insert_function!(function_equals::Equals, "equals" >>> function_map);
     insert_function!(function_get::Get, "get" >>> function_map);
     insert_function!(function_glob::Glob, "glob" >>> function_map);
+    insert_function!(function_hash_file::HashFile, "hash_file" >>> function_map);
+    insert_function!(function_hash_files::HashFiles, "hash_files" >>> function_map);
+    insert_function!(function_hash_string::HashString, "hash_string" >>> function_map);
     insert_function!(function_is_exists::IsExists, "is_exists" >>> function_map);
     insert_function!(function_join_path::JoinPath, "join_path" >>> function_map);
     insert_function!(function_len::Len, "len" >>> function_map);
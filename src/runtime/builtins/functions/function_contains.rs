@@ -1,6 +1,6 @@
 use super::super::super::value::ValueType;
 
-define_function!(Contains(_execution, argument_vec) => {
+define_function!(Contains[2](_execution, argument_vec) => {
 	if argument_vec.len() != 2 {
 		panic!("2 arguments required, got {}.", argument_vec.len())
 	}
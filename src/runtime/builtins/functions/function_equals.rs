@@ -1,6 +1,6 @@
 use super::super::super::value::compare_value;
 
-define_function!(Equals(_execution, argument_vec) => {
+define_function!(Equals[2](_execution, argument_vec) => {
 	if argument_vec.len() != 2 {
 		panic!("2 argument required, got {}.", argument_vec.len())
 	}
@@ -1,6 +1,6 @@
 use super::super::super::value::ValueType;
 
-define_function!(Get(_execution, argument_vec) => {
+define_function!(Get[2](_execution, argument_vec) => {
 	if argument_vec.len() != 2 {
 		panic!("2 arguments required, got {}.", argument_vec.len())
 	}
@@ -2,7 +2,7 @@ use glob::{glob, GlobError};
 use std::fs::canonicalize;
 use std::path::PathBuf;
 
-define_function!(Glob(_execution, argument_vec) => {
+define_function!(Glob[1](execution, argument_vec) => {
 	if argument_vec.len() != 1 {
 		panic!("1 argument required, got {}.", argument_vec.len())
 	}
@@ -10,6 +10,8 @@ define_function!(Glob(_execution, argument_vec) => {
 	Value::Array(
 		match argument_vec[0].to_strict::<String>() {
 			Some(string_value) => {
+				execution.watch_glob(string_value.clone());
+
 				match glob(&string_value).map(|paths| paths.collect::<Vec<Result<PathBuf, GlobError>>>().into_iter().flatten().collect::<Vec<PathBuf>>()) {
 					Ok(path_vec) => path_vec.into_iter().map(|path| canonicalize(path)).flatten().map(|path| path.into_os_string().into_string()).flatten().map(|path| Value::String(path)).collect::<Vec<Value>>(),
 					Err(err) => panic!("wrong glob pattern: {}", err)
@@ -0,0 +1,23 @@
+use super::hash_algorithm::Hasher;
+use std::path::Path;
+
+define_function!(HashFile[2](_execution, argument_vec) => {
+	if argument_vec.len() != 2 {
+		panic!("2 arguments required, got {}.", argument_vec.len())
+	}
+
+	let path = match argument_vec[0].to_strict::<String>() {
+		Some(path) => path,
+		None => panic!("string is required")
+	};
+	let mut hasher = match argument_vec[1].to_strict::<String>() {
+		Some(algorithm) => Hasher::from_name_or_panic(&algorithm),
+		None => panic!("string is required")
+	};
+
+	if let Err(err) = hasher.update_file(Path::new(&path)) {
+		panic!("unable to hash '{}': {}", path, err)
+	}
+
+	Value::String(hasher.finalize())
+});
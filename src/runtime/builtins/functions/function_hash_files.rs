@@ -0,0 +1,65 @@
+use super::hash_algorithm::Hasher;
+use std::path::Path;
+
+define_function!(HashFiles[1](_execution, argument_vec) => {
+	if argument_vec.len() != 1 {
+		panic!("1 argument required, got {}.", argument_vec.len())
+	}
+
+	match argument_vec[0].to_strict::<Vec<String>>() {
+		Some(path_vec) => Value::String(hash_files(path_vec)),
+		None => panic!("array of strings is required")
+	}
+});
+
+// The digest depends on the contents of the files in the order of their paths, but not on the
+// paths themselves, so that the same files give the same digest wherever they are checked out.
+// Directories, e.g. matched by a glob like "src/**/*", are skipped; the files in them count only
+// if they are given as well.
+fn hash_files(mut path_vec: Vec<String>) -> String {
+	path_vec.sort();
+	path_vec.dedup();
+
+	let mut hasher = Hasher::from_name_or_panic("sha256");
+
+	for path in path_vec.iter() {
+		if Path::new(path).is_dir() {
+			continue;
+		}
+
+		let mut file_hasher = Hasher::from_name_or_panic("sha256");
+
+		if let Err(err) = file_hasher.update_file(Path::new(path)) {
+			panic!("unable to hash '{}': {}", path, err)
+		}
+
+		hasher.update(file_hasher.finalize().as_bytes());
+		hasher.update(b"\n");
+	}
+
+	hasher.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::fs::{create_dir_all, remove_dir_all, write};
+	use std::process;
+
+	#[test]
+	fn ignores_the_order_of_the_paths_and_directories() {
+		let directory = std::env::temp_dir().join(format!("piped-hash-files-{}", process::id()));
+		create_dir_all(directory.join("sub")).unwrap();
+		write(directory.join("a"), "a").unwrap();
+		write(directory.join("sub").join("b"), "b").unwrap();
+
+		let path = |name: &str| directory.join(name).to_string_lossy().into_owned();
+		let forward = hash_files(vec![path("a"), path("sub/b")]);
+		let backward = hash_files(vec![path("sub/b"), path("a")]);
+		let with_directory = hash_files(vec![path("sub"), path("a"), path("sub/b")]);
+		remove_dir_all(&directory).unwrap();
+
+		assert_eq!(forward, backward);
+		assert_eq!(forward, with_directory);
+	}
+}
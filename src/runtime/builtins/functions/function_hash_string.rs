@@ -0,0 +1,19 @@
+use super::hash_algorithm::Hasher;
+
+define_function!(HashString[2](_execution, argument_vec) => {
+	if argument_vec.len() != 2 {
+		panic!("2 arguments required, got {}.", argument_vec.len())
+	}
+
+	let string = match argument_vec[0].to_strict::<String>() {
+		Some(string) => string,
+		None => panic!("string is required")
+	};
+	let mut hasher = match argument_vec[1].to_strict::<String>() {
+		Some(algorithm) => Hasher::from_name_or_panic(&algorithm),
+		None => panic!("string is required")
+	};
+
+	hasher.update(string.as_bytes());
+	Value::String(hasher.finalize())
+});
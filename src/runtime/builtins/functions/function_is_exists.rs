@@ -1,4 +1,4 @@
-define_function!(IsExists(execution, argument_vec) => {
+define_function!(IsExists[1](execution, argument_vec) => {
 	if argument_vec.len() != 1 {
 		panic!("1 argument required, got {}.", argument_vec.len())
 	}
@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-define_function!(JoinPath(_execution, argument_vec) => {
+define_function!(JoinPath[..](_execution, argument_vec) => {
 	let mut path = PathBuf::new();
 
 	for argument in argument_vec.into_iter() {
@@ -1,6 +1,6 @@
 use regex::Regex;
 
-define_function!(ReReplace(_execution, argument_vec) => {
+define_function!(ReReplace[3](_execution, argument_vec) => {
 	if argument_vec.len() != 3 {
 		panic!("2 argument required, got {}.", argument_vec.len())
 	}
@@ -1,6 +1,6 @@
 use super::super::super::value::ValueType;
 
-define_function!(Typeof(_execution, argument_vec) => {
+define_function!(Typeof[1](_execution, argument_vec) => {
 	if argument_vec.len() != 1 {
 		panic!("1 argument required, got {}.", argument_vec.len())
 	}
@@ -0,0 +1,123 @@
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{copy, Result, Write};
+use std::path::Path;
+
+// The hash algorithms supported by the hash functions, by the names scripts use for them.
+pub const ALGORITHM_NAMES: [&str; 4] = ["sha256", "sha1", "md5", "blake3"];
+
+pub enum Hasher {
+	Sha256(Sha256),
+	Sha1(Sha1),
+	Md5(Md5),
+	Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+	pub fn from_name(name: &str) -> Option<Hasher> {
+		match name {
+			"sha256" => Some(Hasher::Sha256(Sha256::new())),
+			"sha1" => Some(Hasher::Sha1(Sha1::new())),
+			"md5" => Some(Hasher::Md5(Md5::new())),
+			"blake3" => Some(Hasher::Blake3(Box::new(blake3::Hasher::new()))),
+			_ => None,
+		}
+	}
+
+	// Like from_name, but panics with the supported names if the algorithm is unknown.
+	pub fn from_name_or_panic(name: &str) -> Hasher {
+		match Hasher::from_name(name) {
+			Some(hasher) => hasher,
+			None => panic!(
+				"unknown hash algorithm '{}'; expected one of {}",
+				name,
+				ALGORITHM_NAMES
+					.iter()
+					.map(|name| format!("'{}'", name))
+					.collect::<Vec<_>>()
+					.join(", ")
+			),
+		}
+	}
+
+	pub fn update(&mut self, content: &[u8]) {
+		match self {
+			Hasher::Sha256(hasher) => hasher.update(content),
+			Hasher::Sha1(hasher) => hasher.update(content),
+			Hasher::Md5(hasher) => hasher.update(content),
+			Hasher::Blake3(hasher) => {
+				hasher.update(content);
+			}
+		}
+	}
+
+	// Feeds the content of the file without reading it into memory at once.
+	pub fn update_file(&mut self, path: &Path) -> Result<()> {
+		copy(&mut File::open(path)?, self).map(|_| ())
+	}
+
+	// Returns the digest as a lowercase hex string.
+	pub fn finalize(self) -> String {
+		let digest = match self {
+			Hasher::Sha256(hasher) => hasher.finalize().to_vec(),
+			Hasher::Sha1(hasher) => hasher.finalize().to_vec(),
+			Hasher::Md5(hasher) => hasher.finalize().to_vec(),
+			Hasher::Blake3(hasher) => hasher.finalize().as_bytes().to_vec(),
+		};
+
+		digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+	}
+}
+
+impl Write for Hasher {
+	fn write(&mut self, buf: &[u8]) -> Result<usize> {
+		self.update(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> Result<()> {
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn digest(algorithm: &str, content: &str) -> String {
+		let mut hasher = Hasher::from_name_or_panic(algorithm);
+		hasher.update(content.as_bytes());
+		hasher.finalize()
+	}
+
+	#[test]
+	fn computes_the_known_digests() {
+		for (algorithm, empty, abc) in [
+			(
+				"sha256",
+				"e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+				"ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+			),
+			(
+				"sha1",
+				"da39a3ee5e6b4b0d3255bfef95601890afd80709",
+				"a9993e364706816aba3e25717850c26c9cd0d89d",
+			),
+			(
+				"md5",
+				"d41d8cd98f00b204e9800998ecf8427e",
+				"900150983cd24fb0d6963f7d28e17f72",
+			),
+			(
+				"blake3",
+				"af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262",
+				"6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+			),
+		] {
+			assert_eq!(digest(algorithm, ""), empty, "{}", algorithm);
+			assert_eq!(digest(algorithm, "abc"), abc, "{}", algorithm);
+		}
+	}
+}
@@ -5,8 +5,12 @@ pub mod function_contains;
 pub mod function_equals;
 pub mod function_get;
 pub mod function_glob;
+pub mod function_hash_file;
+pub mod function_hash_files;
+pub mod function_hash_string;
 pub mod function_is_exists;
 pub mod function_join_path;
 pub mod function_len;
 pub mod function_re_replace;
 pub mod function_typeof;
+pub mod hash_algorithm;
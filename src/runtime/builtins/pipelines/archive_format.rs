@@ -0,0 +1,117 @@
+use std::fs::Metadata;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ArchiveFormat {
+	Tar,
+	TarGz,
+	Zip,
+}
+
+impl ArchiveFormat {
+	pub fn from_name(name: &str) -> Option<ArchiveFormat> {
+		match name {
+			"tar" => Some(ArchiveFormat::Tar),
+			"tar.gz" | "tgz" => Some(ArchiveFormat::TarGz),
+			"zip" => Some(ArchiveFormat::Zip),
+			_ => None,
+		}
+	}
+
+	pub fn from_path(path: &str) -> Option<ArchiveFormat> {
+		let path = path.to_lowercase();
+
+		if path.ends_with(".tar.gz") || path.ends_with(".tgz") {
+			Some(ArchiveFormat::TarGz)
+		} else if path.ends_with(".tar") {
+			Some(ArchiveFormat::Tar)
+		} else if path.ends_with(".zip") {
+			Some(ArchiveFormat::Zip)
+		} else {
+			None
+		}
+	}
+
+	// The format given by name if any, or the one the extension of the path implies.
+	pub fn resolve(name: Option<&str>, path: &str) -> ArchiveFormat {
+		match name {
+			Some(name) => match ArchiveFormat::from_name(name) {
+				Some(format) => format,
+				None => panic!(
+					"unknown archive format '{}'; expected one of 'tar', 'tar.gz', 'zip'",
+					name
+				),
+			},
+			None => match ArchiveFormat::from_path(path) {
+				Some(format) => format,
+				None => panic!(
+					"unable to tell the archive format of '{}'; give a 'format' of 'tar', 'tar.gz' or 'zip'",
+					path
+				),
+			},
+		}
+	}
+}
+
+// Archived files only keep whether they are executable, so that archives do not depend on the
+// umask of the machine they are created on.
+#[cfg(unix)]
+pub fn archive_mode(metadata: &Metadata) -> u32 {
+	use std::os::unix::fs::PermissionsExt;
+
+	if metadata.permissions().mode() & 0o111 != 0 {
+		0o755
+	} else {
+		0o644
+	}
+}
+
+#[cfg(not(unix))]
+pub fn archive_mode(_metadata: &Metadata) -> u32 {
+	0o644
+}
+
+// Converts seconds since the Unix epoch into the calendar date and time in UTC.
+pub fn civil_time(timestamp: i64) -> (i64, u32, u32, u32, u32, u32) {
+	let days = timestamp.div_euclid(86400);
+	let seconds = timestamp.rem_euclid(86400) as u32;
+
+	// See http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+	let days = days + 719468;
+	let era = days.div_euclid(146097);
+	let day_of_era = days.rem_euclid(146097);
+	let year_of_era =
+		(day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month_index = (5 * day_of_year + 2) / 153;
+	let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+	let month = if month_index < 10 {
+		month_index + 3
+	} else {
+		month_index - 9
+	} as u32;
+	let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+	(
+		year,
+		month,
+		day,
+		seconds / 3600,
+		seconds / 60 % 60,
+		seconds % 60,
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn converts_timestamps_into_civil_time() {
+		assert_eq!(civil_time(0), (1970, 1, 1, 0, 0, 0));
+		assert_eq!(civil_time(-1), (1969, 12, 31, 23, 59, 59));
+		assert_eq!(civil_time(1709210096), (2024, 2, 29, 12, 34, 56));
+		assert_eq!(civil_time(1709251200), (2024, 3, 1, 0, 0, 0));
+		// The earliest time a zip archive can hold.
+		assert_eq!(civil_time(315532800), (1980, 1, 1, 0, 0, 0));
+	}
+}
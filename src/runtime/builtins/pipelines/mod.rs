@@ -1,4 +1,8 @@
 #[macro_use]
 pub mod pipeline;
 
+pub mod archive_format;
+pub mod pipeline_archive;
+pub mod pipeline_download;
 pub mod pipeline_exec;
+pub mod pipeline_extract;
@@ -1,16 +1,37 @@
-use super::super::super::pipeline::PipelineFactory;
-use super::pipeline_exec;
+use super::super::super::pipeline::{validate_argument_map, PipelineFactory, PipelineParameter};
+use super::{pipeline_archive, pipeline_download, pipeline_exec, pipeline_extract};
 use std::collections::HashMap;
 
 macro_rules! define_pipeline {
-	($name:ident, $argument_map:ident => $body:block) => {
-		use super::super::super::pipeline::{PipelineExecution, PipelineExecutionResult};
+	($name:ident {
+		required: [$($required:ident: $required_type:ident),* $(,)?],
+		optional: [$($optional:ident: $optional_type:ident),* $(,)?] $(,)?
+	}, $argument_map:ident => $body:block) => {
+		use super::super::super::pipeline::{
+			PipelineExecution, PipelineExecutionResult, PipelineParameter,
+		};
 		use super::super::super::value::Value;
 		use std::collections::HashMap;
 
 		pub struct $name {}
 
 		impl $name {
+			pub fn parameter_vec() -> Vec<PipelineParameter> {
+				vec![
+					$(PipelineParameter {
+						name: stringify!($required).to_owned(),
+						value_type: super::super::super::value::ValueType::$required_type,
+						required: true,
+					},)*
+					$(PipelineParameter {
+						name: stringify!($optional).to_owned(),
+						value_type: super::super::super::value::ValueType::$optional_type,
+						required: false,
+					},)*
+				]
+			}
+
+			#[allow(clippy::new_ret_no_self)]
 			pub fn new($argument_map: &HashMap<String, Value>) -> Box<PipelineExecution> $body
 		}
 	};
@@ -18,14 +39,52 @@ macro_rules! define_pipeline {
 
 macro_rules! insert_pipeline {
 	($pipeline:ty, $pipeline_name:literal >>> $pipeline_map:ident) => {
-		$pipeline_map.insert($pipeline_name.to_owned(), Box::new(<$pipeline>::new))
+		let parameter_vec = <$pipeline>::parameter_vec();
+		$pipeline_map.insert(
+			$pipeline_name.to_owned(),
+			Box::new(move |argument_map| {
+				if let Err(err) = validate_argument_map(&parameter_vec, argument_map) {
+					panic!(
+						"invalid arguments for pipeline '{}': {}",
+						$pipeline_name, err
+					);
+				}
+
+				<$pipeline>::new(argument_map)
+			}),
+		)
+	};
+}
+
+macro_rules! insert_pipeline_parameter {
+	($pipeline:ty, $pipeline_name:literal >>> $parameter_map:ident) => {
+		$parameter_map.insert($pipeline_name.to_owned(), <$pipeline>::parameter_vec())
+	};
+}
+
+// Lists every built-in pipeline by name. Both maps below are built from this list, so they can not
+// drift apart.
+macro_rules! insert_builtin_pipelines {
+	($insert:ident >>> $map:ident) => {
+		$insert!(pipeline_archive::Archive, "archive" >>> $map);
+		$insert!(pipeline_download::Download, "download" >>> $map);
+		$insert!(pipeline_exec::Exec, "exec" >>> $map);
+		$insert!(pipeline_extract::Extract, "extract" >>> $map);
 	};
 }
 
 pub fn build_pipeline_map() -> HashMap<String, Box<PipelineFactory>> {
 	let mut pipeline_map: HashMap<_, Box<PipelineFactory>> = HashMap::new();
 
-	insert_pipeline!(pipeline_exec::Exec, "exec" >>> pipeline_map);
+	insert_builtin_pipelines!(insert_pipeline >>> pipeline_map);
 
 	pipeline_map
 }
+
+pub fn build_pipeline_parameter_map() -> HashMap<String, Vec<PipelineParameter>> {
+	let mut parameter_map = HashMap::new();
+
+	insert_builtin_pipelines!(insert_pipeline_parameter >>> parameter_map);
+
+	parameter_map
+}
@@ -0,0 +1,349 @@
+use super::super::super::value::ValueType;
+use super::archive_format::{archive_mode, civil_time, ArchiveFormat};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use glob::glob;
+use path_absolutize::Absolutize;
+use std::env::var;
+use std::fs::{create_dir_all, metadata, read_dir, File};
+use std::io::{copy, Write};
+use std::path::{Path, PathBuf};
+use tar::{Builder, EntryType, Header};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, DateTime, ZipWriter};
+
+struct ArchiveEntry {
+	name: String,
+	path: PathBuf,
+	mode: u32,
+}
+
+define_pipeline!(Archive {
+	required: [output: String, files: Array],
+	optional: [base: String, format: String, mtime: Integer],
+}, argument_map => {
+	let output = argument_map["output"].to_strict::<String>().unwrap();
+	let files = match argument_map["files"].to_strict::<Vec<String>>() {
+		Some(files) => files,
+		None => panic!(
+			"'{}' must be a '{:#?}' of '{:#?}' type",
+			"files",
+			ValueType::Array,
+			ValueType::String
+		),
+	};
+	let base = argument_map
+		.get("base")
+		.map(|base| base.to_strict::<String>().unwrap())
+		.unwrap_or_else(|| ".".to_owned());
+	let format = ArchiveFormat::resolve(
+		argument_map
+			.get("format")
+			.map(|format| format.to_strict::<String>().unwrap())
+			.as_deref(),
+		&output,
+	);
+	// Every entry gets the same timestamp, so that archives of the same files are identical.
+	let mtime = match argument_map.get("mtime") {
+		Some(mtime) => mtime.to_strict::<i64>().unwrap(),
+		None => var("SOURCE_DATE_EPOCH")
+			.ok()
+			.and_then(|epoch| epoch.parse::<i64>().ok())
+			.unwrap_or(0),
+	};
+
+	Box::new(move || -> PipelineExecutionResult {
+		let entry_vec = match collect_entries(&files, Path::new(&base), Path::new(&output)) {
+			Ok(entry_vec) => entry_vec,
+			Err(err) => panic!("unable to archive into '{}': {}", output, err),
+		};
+
+		if let Err(err) = write_archive(Path::new(&output), format, &entry_vec, mtime) {
+			panic!("unable to archive into '{}': {}", output, err);
+		}
+
+		PipelineExecutionResult {
+			success: true,
+			result: Some(Value::Array(
+				entry_vec
+					.into_iter()
+					.map(|entry| Value::String(entry.name))
+					.collect(),
+			)),
+		}
+	})
+});
+
+// Expands the files, which may be paths or glob patterns, into entries named by their paths
+// relative to the base directory, sorted by name. Directories are archived with all their files,
+// except for the output itself and symbolic links to directories, which could form a loop.
+fn collect_entries(
+	file_vec: &[String],
+	base: &Path,
+	output: &Path,
+) -> Result<Vec<ArchiveEntry>, String> {
+	let base = base
+		.absolutize()
+		.map_err(|err| err.to_string())?
+		.to_path_buf();
+	let output = output
+		.absolutize()
+		.map_err(|err| err.to_string())?
+		.to_path_buf();
+	let mut path_vec = Vec::new();
+
+	for file in file_vec.iter() {
+		if file.contains(['*', '?', '[']) {
+			let matched_vec = glob(file)
+				.map_err(|err| format!("wrong glob pattern '{}': {}", file, err))?
+				.flatten()
+				.collect::<Vec<_>>();
+
+			if matched_vec.is_empty() {
+				return Err(format!("'{}' matches no files", file));
+			}
+
+			path_vec.extend(matched_vec);
+		} else if Path::new(file).exists() {
+			path_vec.push(PathBuf::from(file));
+		} else {
+			return Err(format!("'{}' does not exist", file));
+		}
+	}
+
+	let mut entry_vec = Vec::new();
+
+	while let Some(path) = path_vec.pop() {
+		let metadata = metadata(&path)
+			.map_err(|err| format!("unable to read '{}': {}", path.display(), err))?;
+
+		if metadata.is_dir() {
+			for entry in read_dir(&path)
+				.map_err(|err| format!("unable to read '{}': {}", path.display(), err))?
+			{
+				let entry = entry.map_err(|err| err.to_string())?;
+				let is_symlink = entry
+					.file_type()
+					.map_err(|err| err.to_string())?
+					.is_symlink();
+
+				if !(is_symlink && entry.path().is_dir()) {
+					path_vec.push(entry.path());
+				}
+			}
+			continue;
+		}
+
+		let absolute_path = path
+			.absolutize()
+			.map_err(|err| err.to_string())?
+			.to_path_buf();
+
+		if absolute_path == output {
+			continue;
+		}
+		let name = match absolute_path.strip_prefix(&base) {
+			Ok(relative_path) => relative_path
+				.components()
+				.map(|component| component.as_os_str().to_string_lossy())
+				.collect::<Vec<_>>()
+				.join("/"),
+			Err(..) => {
+				return Err(format!(
+					"'{}' is not in the base directory '{}'",
+					path.display(),
+					base.display()
+				))
+			}
+		};
+
+		entry_vec.push(ArchiveEntry {
+			name,
+			path,
+			mode: archive_mode(&metadata),
+		});
+	}
+
+	entry_vec.sort_by(|left, right| left.name.cmp(&right.name));
+	entry_vec.dedup_by(|left, right| left.name == right.name);
+	Ok(entry_vec)
+}
+
+fn write_archive(
+	output: &Path,
+	format: ArchiveFormat,
+	entry_vec: &[ArchiveEntry],
+	mtime: i64,
+) -> Result<(), String> {
+	if let Some(parent) = output.parent() {
+		create_dir_all(parent).map_err(|err| err.to_string())?;
+	}
+
+	let file = File::create(output).map_err(|err| err.to_string())?;
+
+	match format {
+		ArchiveFormat::Tar => write_tar(file, entry_vec, mtime).map(|_| ()),
+		ArchiveFormat::TarGz => write_tar(
+			GzEncoder::new(file, Compression::default()),
+			entry_vec,
+			mtime,
+		)
+		.and_then(|encoder| encoder.finish().map_err(|err| err.to_string()))
+		.map(|_| ()),
+		ArchiveFormat::Zip => write_zip(file, entry_vec, mtime),
+	}
+}
+
+fn write_tar<W: Write>(writer: W, entry_vec: &[ArchiveEntry], mtime: i64) -> Result<W, String> {
+	let mut builder = Builder::new(writer);
+
+	for entry in entry_vec.iter() {
+		let file = File::open(&entry.path)
+			.map_err(|err| format!("unable to read '{}': {}", entry.path.display(), err))?;
+		let mut header = Header::new_gnu();
+		header.set_entry_type(EntryType::Regular);
+		header.set_size(file.metadata().map_err(|err| err.to_string())?.len());
+		header.set_mode(entry.mode);
+		header.set_uid(0);
+		header.set_gid(0);
+		header.set_mtime(mtime.max(0) as u64);
+
+		builder
+			.append_data(&mut header, &entry.name, file)
+			.map_err(|err| format!("unable to archive '{}': {}", entry.path.display(), err))?;
+	}
+
+	builder.into_inner().map_err(|err| err.to_string())
+}
+
+fn write_zip(file: File, entry_vec: &[ArchiveEntry], mtime: i64) -> Result<(), String> {
+	// Zip archives cannot hold timestamps before 1980.
+	let (year, month, day, hour, minute, second) = civil_time(mtime.max(315532800));
+	let last_modified_time = DateTime::from_date_and_time(
+		year.min(2107) as u16,
+		month as u8,
+		day as u8,
+		hour as u8,
+		minute as u8,
+		second as u8,
+	)
+	.unwrap_or_default();
+	let mut writer = ZipWriter::new(file);
+
+	for entry in entry_vec.iter() {
+		let mut file = File::open(&entry.path)
+			.map_err(|err| format!("unable to read '{}': {}", entry.path.display(), err))?;
+
+		writer
+			.start_file(
+				entry.name.as_str(),
+				FileOptions::default()
+					.compression_method(CompressionMethod::Deflated)
+					.last_modified_time(last_modified_time)
+					.unix_permissions(entry.mode),
+			)
+			.map_err(|err| err.to_string())?;
+		copy(&mut file, &mut writer)
+			.map_err(|err| format!("unable to archive '{}': {}", entry.path.display(), err))?;
+	}
+
+	writer.finish().map(|_| ()).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::pipeline_extract::extract_archive;
+	use super::*;
+	use std::fs::{read, remove_dir_all, write};
+	use std::process;
+
+	const FORMATS: [(ArchiveFormat, &str); 3] = [
+		(ArchiveFormat::Tar, "tar"),
+		(ArchiveFormat::TarGz, "tar.gz"),
+		(ArchiveFormat::Zip, "zip"),
+	];
+
+	// Creates a directory with the given files, in the given order.
+	fn create_tree(name: &str, file_vec: &[(&str, &str)]) -> PathBuf {
+		let directory =
+			std::env::temp_dir().join(format!("piped-archive-{}-{}", name, process::id()));
+
+		for (path, content) in file_vec.iter() {
+			let path = directory.join(path);
+			create_dir_all(path.parent().unwrap()).unwrap();
+			write(path, content).unwrap();
+		}
+
+		directory
+	}
+
+	fn archive(directory: &Path, output: &Path, format: ArchiveFormat) -> Vec<String> {
+		let entry_vec = collect_entries(
+			&[directory.join("src").to_string_lossy().into_owned()],
+			directory,
+			output,
+		)
+		.unwrap();
+		write_archive(output, format, &entry_vec, 0).unwrap();
+
+		entry_vec.into_iter().map(|entry| entry.name).collect()
+	}
+
+	#[test]
+	fn extracts_what_was_archived() {
+		let directory = create_tree("round-trip", &[("src/a", "a"), ("src/sub/b", "b")]);
+
+		for (format, extension) in FORMATS.iter() {
+			let output = directory.join(format!("out.{}", extension));
+			let dst = directory.join(format!("dst-{}", extension));
+
+			assert_eq!(
+				archive(&directory, &output, *format),
+				["src/a", "src/sub/b"]
+			);
+			extract_archive(&output, *format, &dst).unwrap();
+			assert_eq!(read(dst.join("src/a")).unwrap(), b"a");
+			assert_eq!(read(dst.join("src/sub/b")).unwrap(), b"b");
+		}
+
+		remove_dir_all(&directory).unwrap();
+	}
+
+	#[test]
+	fn creates_identical_archives_of_the_same_files() {
+		let forward = create_tree("forward", &[("src/a", "a"), ("src/sub/b", "b")]);
+		let backward = create_tree("backward", &[("src/sub/b", "b"), ("src/a", "a")]);
+
+		for (format, extension) in FORMATS.iter() {
+			let forward_output = forward.join(format!("out.{}", extension));
+			let backward_output = backward.join(format!("out.{}", extension));
+			archive(&forward, &forward_output, *format);
+			archive(&backward, &backward_output, *format);
+
+			assert_eq!(
+				read(&forward_output).unwrap(),
+				read(&backward_output).unwrap(),
+				"{}",
+				extension
+			);
+		}
+
+		remove_dir_all(&forward).unwrap();
+		remove_dir_all(&backward).unwrap();
+	}
+
+	#[test]
+	fn skips_the_output_and_symbolic_links_to_directories() {
+		let directory = create_tree("skip", &[("src/a", "a")]);
+		let output = directory.join("src").join("out.tar");
+		#[cfg(unix)]
+		std::os::unix::fs::symlink(directory.join("src"), directory.join("src").join("loop"))
+			.unwrap();
+
+		archive(&directory, &output, ArchiveFormat::Tar);
+		let name_vec = archive(&directory, &output, ArchiveFormat::Tar);
+		remove_dir_all(&directory).unwrap();
+
+		assert_eq!(name_vec, ["src/a"]);
+	}
+}
@@ -0,0 +1,278 @@
+use super::super::functions::hash_algorithm::Hasher;
+use std::fs::{create_dir_all, remove_file, rename, File};
+use std::io::{ErrorKind, Read, Result as IoResult, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::sleep;
+use std::time::Duration;
+use ureq::{AgentBuilder, Error};
+
+// Numbers the temporary files of the process, so that concurrent downloads never share one.
+static DOWNLOAD_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+define_pipeline!(Download {
+	required: [url: String, dst: String],
+	optional: [sha256: String, retries: Integer, timeout: Integer],
+}, argument_map => {
+	let url = argument_map["url"].to_strict::<String>().unwrap();
+	let dst = PathBuf::from(argument_map["dst"].to_strict::<String>().unwrap());
+	let sha256 = argument_map.get("sha256").map(|sha256| {
+		let sha256 = sha256.to_strict::<String>().unwrap().to_lowercase();
+
+		if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+			panic!("'sha256' must be 64 hex digits, got '{}'", sha256);
+		}
+
+		sha256
+	});
+	let retries = match argument_map.get("retries").map(|retries| retries.to_strict::<i64>().unwrap()) {
+		Some(retries) if retries < 0 => panic!("'retries' must not be negative, got {}", retries),
+		Some(retries) => retries as u32,
+		None => 3,
+	};
+	let timeout = match argument_map.get("timeout").map(|timeout| timeout.to_strict::<i64>().unwrap()) {
+		Some(timeout) if timeout <= 0 => panic!("'timeout' must be positive, got {}", timeout),
+		Some(timeout) => Duration::from_secs(timeout as u64),
+		None => Duration::from_secs(300),
+	};
+
+	Box::new(move || -> PipelineExecutionResult {
+		// Nothing to do if the destination is already what would be downloaded.
+		if let Some(sha256) = &sha256 {
+			if hash_file(&dst).ok().as_ref() == Some(sha256) {
+				return PipelineExecutionResult {
+					success: true,
+					result: Some(Value::String(sha256.clone())),
+				};
+			}
+		}
+
+		match download(&url, &dst, sha256.as_deref(), retries, timeout) {
+			Ok(hash) => PipelineExecutionResult {
+				success: true,
+				result: Some(Value::String(hash)),
+			},
+			Err(err) => panic!("unable to download '{}': {}", url, err),
+		}
+	})
+});
+
+enum DownloadError {
+	// Worth another attempt, e.g. a timeout or a server error.
+	Transient(String),
+	Permanent(String),
+}
+
+// Downloads the url into the destination through a temporary file next to it, so that the
+// destination never holds a partial or unverified download, and returns its SHA-256 digest.
+fn download(
+	url: &str,
+	dst: &Path,
+	sha256: Option<&str>,
+	retries: u32,
+	timeout: Duration,
+) -> Result<String, String> {
+	if let Some(parent) = dst.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+		create_dir_all(parent)
+			.map_err(|err| format!("unable to create '{}': {}", parent.display(), err))?;
+	}
+
+	let temporary_path = dst.with_file_name(format!(
+		".{}.download-{}-{}",
+		dst.file_name()
+			.map(|file_name| file_name.to_string_lossy().into_owned())
+			.unwrap_or_default(),
+		process::id(),
+		DOWNLOAD_COUNT.fetch_add(1, Ordering::SeqCst)
+	));
+	// The timeout applies to connecting and to every read, so that large downloads that keep
+	// making progress are not cut off.
+	let agent = AgentBuilder::new()
+		.timeout_connect(timeout)
+		.timeout_read(timeout)
+		.build();
+	let mut attempt = 0;
+
+	let hash = loop {
+		let result = agent
+			.get(url)
+			.call()
+			.map_err(|err| match err {
+				Error::Status(status, ..) if status < 500 && status != 429 => {
+					DownloadError::Permanent(err.to_string())
+				}
+				_ => DownloadError::Transient(err.to_string()),
+			})
+			.and_then(|response| {
+				write_hashed(&mut response.into_reader(), &temporary_path)
+					.map_err(|err| DownloadError::Transient(err.to_string()))
+			});
+
+		match result {
+			Ok(hash) => break hash,
+			Err(DownloadError::Transient(err)) if attempt < retries => {
+				attempt += 1;
+				eprintln!(
+					"Unable to download '{}'; retrying ({}/{}).",
+					url, attempt, retries
+				);
+				eprintln!("\tbecause: {}", err);
+				sleep(Duration::from_secs(1 << (attempt - 1).min(5)));
+			}
+			Err(DownloadError::Transient(err)) | Err(DownloadError::Permanent(err)) => {
+				let _ = remove_file(&temporary_path);
+				return Err(err);
+			}
+		}
+	};
+
+	if let Some(sha256) = sha256 {
+		if hash != sha256 {
+			let _ = remove_file(&temporary_path);
+			return Err(format!(
+				"the checksum does not match; expected {}, got {}",
+				sha256, hash
+			));
+		}
+	}
+
+	rename(&temporary_path, dst).map_err(|err| {
+		let _ = remove_file(&temporary_path);
+		format!("unable to write '{}': {}", dst.display(), err)
+	})?;
+
+	Ok(hash)
+}
+
+// Writes the content into the file while hashing it, and returns its SHA-256 digest.
+fn write_hashed(reader: &mut impl Read, path: &Path) -> IoResult<String> {
+	let mut file = File::create(path)?;
+	let mut hasher = Hasher::from_name("sha256").unwrap();
+	let mut buffer = vec![0; 64 * 1024];
+
+	loop {
+		let length = match reader.read(&mut buffer) {
+			Ok(0) => break,
+			Ok(length) => length,
+			Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+			Err(err) => return Err(err),
+		};
+
+		file.write_all(&buffer[..length])?;
+		hasher.update(&buffer[..length]);
+	}
+
+	file.sync_all()?;
+
+	Ok(hasher.finalize())
+}
+
+fn hash_file(path: &Path) -> IoResult<String> {
+	let mut hasher = Hasher::from_name("sha256").unwrap();
+	hasher.update_file(path)?;
+
+	Ok(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::fs::{read_dir, read_to_string, remove_dir_all};
+	use std::io::{BufRead, BufReader};
+	use std::net::TcpListener;
+	use std::thread::{spawn, JoinHandle};
+
+	const CONTENT: &str = "hello, piped!";
+	// The SHA-256 digest of CONTENT.
+	const CONTENT_SHA256: &str = "5e3a30379143e4a90df12cd23d8bf9f4bcfdbe14c63b066b2f2f5406034c715f";
+
+	// Serves the given statuses to one request each, and returns the URL along with a handle that
+	// yields how many requests were served.
+	fn serve(status_vec: Vec<u16>) -> (String, JoinHandle<usize>) {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let url = format!("http://{}/file.txt", listener.local_addr().unwrap());
+		let handle = spawn(move || {
+			let mut served = 0;
+
+			for status in status_vec {
+				let mut stream = listener.accept().unwrap().0;
+				let mut reader = BufReader::new(stream.try_clone().unwrap());
+				let mut line = String::new();
+
+				while reader.read_line(&mut line).unwrap() != 0 && line != "\r\n" {
+					line.clear();
+				}
+
+				let body = if status == 200 { CONTENT } else { "" };
+				write!(
+					stream,
+					"HTTP/1.1 {} Status\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+					status,
+					body.len(),
+					body
+				)
+				.unwrap();
+				served += 1;
+			}
+
+			served
+		});
+
+		(url, handle)
+	}
+
+	fn directory(name: &str) -> PathBuf {
+		let directory =
+			std::env::temp_dir().join(format!("piped-download-{}-{}", name, process::id()));
+		let _ = remove_dir_all(&directory);
+		directory
+	}
+
+	#[test]
+	fn retries_server_errors() {
+		let (url, handle) = serve(vec![503, 200]);
+		let directory = directory("retry");
+		let dst = directory.join("file.txt");
+		let result = download(&url, &dst, None, 1, Duration::from_secs(5));
+		let content = read_to_string(&dst);
+		remove_dir_all(&directory).unwrap();
+
+		assert_eq!(handle.join().unwrap(), 2);
+		assert_eq!(result.unwrap(), CONTENT_SHA256);
+		assert_eq!(content.unwrap(), CONTENT);
+	}
+
+	#[test]
+	fn does_not_retry_client_errors() {
+		let (url, handle) = serve(vec![404]);
+		let directory = directory("client-error");
+		let result = download(
+			&url,
+			&directory.join("file.txt"),
+			None,
+			3,
+			Duration::from_secs(5),
+		);
+
+		assert_eq!(handle.join().unwrap(), 1);
+		assert!(result.is_err());
+		let _ = remove_dir_all(&directory);
+	}
+
+	#[test]
+	fn leaves_nothing_behind_on_a_checksum_mismatch() {
+		let (url, handle) = serve(vec![200]);
+		let directory = directory("mismatch");
+		let dst = directory.join("file.txt");
+		let result = download(&url, &dst, Some(&"0".repeat(64)), 0, Duration::from_secs(5));
+		let entry_count = read_dir(&directory).unwrap().count();
+		remove_dir_all(&directory).unwrap();
+
+		handle.join().unwrap();
+		assert!(result
+			.unwrap_err()
+			.starts_with("the checksum does not match"));
+		assert_eq!(entry_count, 0);
+	}
+}
@@ -1,14 +1,13 @@
+use super::super::super::action_cache::{is_cache_enabled, Action, ActionCache};
+use super::super::super::output::run_command;
 use super::super::super::value::ValueType;
 use std::process::Command;
 
-define_pipeline!(Exec, argument_map => {
-	let cmd = match argument_map.get("cmd") {
-		Some(cmd) => match cmd.to_strict::<String>() {
-			Some(cmd) => cmd,
-			None => panic!("'{}' must be a '{:#?}' type", "cmd", ValueType::String),
-		},
-		None => panic!("'{}' is requied", "cmd"),
-	};
+define_pipeline!(Exec {
+	required: [cmd: String],
+	optional: [params: Array, envs: Dictionary, inputs: Array, outputs: Array],
+}, argument_map => {
+	let cmd = argument_map["cmd"].to_strict::<String>().unwrap();
 	let params = match argument_map.get("params") {
 		Some(params) => match params.to_strict::<Vec<String>>() {
 			Some(params) => params,
@@ -33,26 +32,90 @@ define_pipeline!(Exec, argument_map => {
 		},
 		None => HashMap::new(),
 	};
+	let inputs = match argument_map.get("inputs") {
+		Some(inputs) => match inputs.to_strict::<Vec<String>>() {
+			Some(inputs) => inputs,
+			None => panic!(
+				"'{}' must be a '{:#?}' of '{:#?}' type",
+				"inputs",
+				ValueType::Array,
+				ValueType::String
+			),
+		},
+		None => vec![],
+	};
+	let outputs = match argument_map.get("outputs") {
+		Some(outputs) => match outputs.to_strict::<Vec<String>>() {
+			Some(outputs) => outputs,
+			None => panic!(
+				"'{}' must be a '{:#?}' of '{:#?}' type",
+				"outputs",
+				ValueType::Array,
+				ValueType::String
+			),
+		},
+		None => vec![],
+	};
 
-	let mut command = Command::new(cmd);
+	let mut command = Command::new(&cmd);
 
 	if !params.is_empty() {
-		command.args(params);
+		command.args(&params);
 	}
 
 	if !envs.is_empty() {
-		command.envs(envs);
+		command.envs(&envs);
 	}
 
 	Box::new(move || -> PipelineExecutionResult {
+		// Only actions that declare their outputs are cached, as there is nothing to restore
+		// otherwise. The key is computed right before running, once the inputs are in place.
+		if outputs.is_empty() || !is_cache_enabled() {
+			return PipelineExecutionResult {
+				success: run_command(&mut command, &cmd),
+				result: None,
+			};
+		}
+
+		let cache = ActionCache::open();
+		let key = match cache.action_key(&Action {
+			cmd: &cmd,
+			params: &params,
+			envs: &envs,
+			input_vec: &inputs,
+			output_vec: &outputs,
+		}) {
+			Ok(key) => key,
+			Err(err) => panic!("unable to compute the cache key of '{}': {}", cmd, err),
+		};
+
+		match cache.restore(&key, &outputs) {
+			Ok(true) => {
+				println!("[cache] restored the outputs of '{}' ({})", cmd, &key[..12]);
+
+				return PipelineExecutionResult {
+					success: true,
+					result: None,
+				};
+			}
+			Ok(false) => {}
+			Err(err) => {
+				eprintln!("Unable to restore the outputs of '{}' from the cache.", cmd);
+				eprintln!("\tbecause: {}", err);
+			}
+		}
+
+		let success = run_command(&mut command, &cmd);
+
+		if success {
+			if let Err(err) = cache.store(&key, &outputs) {
+				eprintln!("Unable to store the outputs of '{}' in the cache.", cmd);
+				eprintln!("\tbecause: {}", err);
+			}
+		}
+
 		PipelineExecutionResult {
-			success: match command.spawn() {
-				Ok(child) => match child.wait_with_output() {
-					Ok(..) => true,
-					Err(..) => false,
-				},
-				Err(..) => false,
-			},
+			success,
 			result: None,
 		}
 	})
@@ -0,0 +1,123 @@
+use super::archive_format::ArchiveFormat;
+use flate2::read::GzDecoder;
+use std::fs::{create_dir_all, File};
+use std::io::{copy, Read};
+use std::path::Path;
+use tar::EntryType;
+use zip::ZipArchive;
+
+define_pipeline!(Extract {
+	required: [archive: String, dst: String],
+	optional: [format: String],
+}, argument_map => {
+	let archive = argument_map["archive"].to_strict::<String>().unwrap();
+	let dst = argument_map["dst"].to_strict::<String>().unwrap();
+	let format = ArchiveFormat::resolve(
+		argument_map
+			.get("format")
+			.map(|format| format.to_strict::<String>().unwrap())
+			.as_deref(),
+		&archive,
+	);
+
+	Box::new(move || -> PipelineExecutionResult {
+		let file_vec = match extract_archive(Path::new(&archive), format, Path::new(&dst)) {
+			Ok(file_vec) => file_vec,
+			Err(err) => panic!("unable to extract '{}': {}", archive, err),
+		};
+
+		PipelineExecutionResult {
+			success: true,
+			result: Some(Value::Array(file_vec.into_iter().map(Value::String).collect())),
+		}
+	})
+});
+
+// Extracts the archive into the destination directory, and returns the paths of the extracted
+// files. Entries that would be extracted outside of the destination are rejected.
+pub fn extract_archive(
+	archive: &Path,
+	format: ArchiveFormat,
+	dst: &Path,
+) -> Result<Vec<String>, String> {
+	let file = File::open(archive).map_err(|err| err.to_string())?;
+	create_dir_all(dst).map_err(|err| format!("unable to create '{}': {}", dst.display(), err))?;
+
+	match format {
+		ArchiveFormat::Tar => extract_tar(file, dst),
+		ArchiveFormat::TarGz => extract_tar(GzDecoder::new(file), dst),
+		ArchiveFormat::Zip => extract_zip(file, dst),
+	}
+}
+
+fn extract_tar<R: Read>(reader: R, dst: &Path) -> Result<Vec<String>, String> {
+	let mut archive = tar::Archive::new(reader);
+	let mut file_vec = Vec::new();
+
+	for entry in archive.entries().map_err(|err| err.to_string())? {
+		let mut entry = entry.map_err(|err| err.to_string())?;
+		let path = entry.path().map_err(|err| err.to_string())?.into_owned();
+
+		if !entry
+			.unpack_in(dst)
+			.map_err(|err| format!("unable to extract '{}': {}", path.display(), err))?
+		{
+			return Err(format!(
+				"'{}' points outside of the destination",
+				path.display()
+			));
+		}
+
+		if entry.header().entry_type() != EntryType::Directory {
+			file_vec.push(dst.join(path).to_string_lossy().into_owned());
+		}
+	}
+
+	Ok(file_vec)
+}
+
+fn extract_zip(file: File, dst: &Path) -> Result<Vec<String>, String> {
+	let mut archive = ZipArchive::new(file).map_err(|err| err.to_string())?;
+	let mut file_vec = Vec::new();
+
+	for index in 0..archive.len() {
+		let mut entry = archive.by_index(index).map_err(|err| err.to_string())?;
+		let path = match entry.enclosed_name() {
+			Some(path) => dst.join(path),
+			None => {
+				return Err(format!(
+					"'{}' points outside of the destination",
+					entry.name()
+				))
+			}
+		};
+
+		if entry.is_dir() {
+			create_dir_all(&path)
+				.map_err(|err| format!("unable to create '{}': {}", path.display(), err))?;
+			continue;
+		}
+
+		if let Some(parent) = path.parent() {
+			create_dir_all(parent)
+				.map_err(|err| format!("unable to create '{}': {}", parent.display(), err))?;
+		}
+
+		File::create(&path)
+			.and_then(|mut file| copy(&mut entry, &mut file))
+			.map_err(|err| format!("unable to extract '{}': {}", path.display(), err))?;
+
+		#[cfg(unix)]
+		if let Some(mode) = entry.unix_mode() {
+			use std::fs::{set_permissions, Permissions};
+			use std::os::unix::fs::PermissionsExt;
+
+			set_permissions(&path, Permissions::from_mode(mode & 0o777))
+				.map_err(|err| format!("unable to extract '{}': {}", path.display(), err))?;
+		}
+
+		file_vec.push(path.to_string_lossy().into_owned());
+	}
+
+	Ok(file_vec)
+}
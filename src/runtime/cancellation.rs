@@ -0,0 +1,63 @@
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+// Asks the commands run on behalf of a pipeline to stop. Cancelling a token cancels every token
+// derived from it, but not its parent.
+#[derive(Clone)]
+pub struct CancellationToken {
+	cancelled: Arc<AtomicBool>,
+	parent: Option<Arc<CancellationToken>>,
+}
+
+impl CancellationToken {
+	fn new() -> CancellationToken {
+		CancellationToken {
+			cancelled: Arc::new(AtomicBool::new(false)),
+			parent: None,
+		}
+	}
+
+	pub fn child(&self) -> CancellationToken {
+		CancellationToken {
+			cancelled: Arc::new(AtomicBool::new(false)),
+			parent: Some(Arc::new(self.clone())),
+		}
+	}
+
+	pub fn cancel(&self) {
+		self.cancelled.store(true, Ordering::SeqCst);
+	}
+
+	// Whether this is the token every thread starts with, which is never cancelled.
+	pub fn is_root(&self) -> bool {
+		self.parent.is_none()
+	}
+
+	pub fn is_cancelled(&self) -> bool {
+		self.cancelled.load(Ordering::SeqCst)
+			|| self
+				.parent
+				.as_ref()
+				.is_some_and(|parent| parent.is_cancelled())
+	}
+}
+
+thread_local! {
+	// The token the commands of the current thread observe; every thread starts with the root.
+	static CURRENT: RefCell<CancellationToken> = RefCell::new(root_token().clone());
+}
+
+fn root_token() -> &'static CancellationToken {
+	static ROOT: OnceLock<CancellationToken> = OnceLock::new();
+	ROOT.get_or_init(CancellationToken::new)
+}
+
+pub fn current_cancellation() -> CancellationToken {
+	CURRENT.with(|current| current.borrow().clone())
+}
+
+// Makes the commands of the current thread observe the given token.
+pub fn enter_cancellation(token: CancellationToken) {
+	CURRENT.with(|current| *current.borrow_mut() = token);
+}
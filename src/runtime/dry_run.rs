@@ -0,0 +1,173 @@
+use super::pipeline::{PipelineExecutionResult, PipelineFactory};
+use super::value::Value;
+use std::collections::HashMap;
+use std::env::current_dir;
+
+// Wraps a built-in pipeline so that it validates its arguments and describes what it would do
+// instead of doing it. Built-in pipelines only have side effects once their execution runs.
+pub fn wrap_builtin_pipeline(name: String, factory: Box<PipelineFactory>) -> Box<PipelineFactory> {
+	Box::new(move |argument_map| {
+		drop(factory(argument_map));
+
+		let description = describe_pipeline(&name, argument_map);
+		let result = placeholder_result(&name, argument_map);
+
+		Box::new(move || {
+			println!("{}", description);
+
+			PipelineExecutionResult {
+				success: true,
+				result: result.clone(),
+			}
+		})
+	})
+}
+
+// Wraps an imported pipeline so that its invocation is logged. The imported pipeline still runs,
+// so that the built-in pipelines it invokes are described as well.
+pub fn wrap_imported_pipeline(name: String, factory: Box<PipelineFactory>) -> Box<PipelineFactory> {
+	Box::new(move |argument_map| {
+		let description = describe_pipeline(&name, argument_map);
+		let mut execution = factory(argument_map);
+
+		Box::new(move || {
+			println!("{}", description);
+			execution()
+		})
+	})
+}
+
+// Stands in for the result of a built-in pipeline, with the shape the real one would have, so that
+// the statements using it are evaluated as they would be.
+fn placeholder_result(name: &str, argument_map: &HashMap<String, Value>) -> Option<Value> {
+	match name {
+		"download" => Some(Value::String(match argument_map.get("sha256") {
+			Some(sha256) => sha256.to_string().to_lowercase(),
+			None => "0".repeat(64),
+		})),
+		"archive" => argument_map.get("files").cloned(),
+		"extract" => Some(Value::Array(Vec::new())),
+		_ => None,
+	}
+}
+
+fn describe_pipeline(name: &str, argument_map: &HashMap<String, Value>) -> String {
+	match name {
+		"exec" => describe_exec(argument_map),
+		_ => {
+			let mut name_vec = argument_map.keys().collect::<Vec<_>>();
+			name_vec.sort();
+
+			let mut description = format!("[dry-run] {}", name);
+
+			for name in name_vec {
+				description += &format!(" {}={}", name, quote(&argument_map[name].to_string()));
+			}
+
+			description
+		}
+	}
+}
+
+fn describe_exec(argument_map: &HashMap<String, Value>) -> String {
+	let mut command_line = quote(&argument_map["cmd"].to_string());
+
+	if let Some(Value::Array(params)) = argument_map.get("params") {
+		for param in params.iter() {
+			command_line += " ";
+			command_line += &quote(&param.to_string());
+		}
+	}
+
+	let mut description = format!("[dry-run] exec: {}", command_line);
+
+	if let Ok(working_directory) = current_dir() {
+		description += &format!("\n\tcwd: {}", working_directory.display());
+	}
+
+	if let Some(Value::Dictionary(envs)) = argument_map.get("envs") {
+		let mut env_vec = envs.iter().collect::<Vec<_>>();
+		env_vec.sort_by(|left, right| left.0.cmp(right.0));
+
+		for (key, value) in env_vec {
+			description += &format!("\n\tenv: {}={}", key, quote(&value.to_string()));
+		}
+	}
+
+	description
+}
+
+// Quotes a word the way a shell would need it, so that the printed command line is unambiguous.
+fn quote(word: &str) -> String {
+	if !word.is_empty()
+		&& word
+			.chars()
+			.all(|character| character.is_alphanumeric() || "-_./:=+,@%".contains(character))
+	{
+		word.to_owned()
+	} else {
+		format!("'{}'", word.replace('\'', "'\\''"))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::builtins::pipelines::pipeline::build_pipeline_map;
+	use super::*;
+
+	fn argument_map(argument_vec: Vec<(&str, Value)>) -> HashMap<String, Value> {
+		argument_vec
+			.into_iter()
+			.map(|(name, value)| (name.to_owned(), value))
+			.collect()
+	}
+
+	#[test]
+	fn returns_placeholders_of_the_shape_of_the_results() {
+		let sha256 = "AB".repeat(32);
+
+		match placeholder_result(
+			"download",
+			&argument_map(vec![
+				("url", Value::String("https://example.com/a".to_owned())),
+				("dst", Value::String("a".to_owned())),
+				("sha256", Value::String(sha256.clone())),
+			]),
+		) {
+			Some(Value::String(hash)) => assert_eq!(hash, sha256.to_lowercase()),
+			_ => panic!("expected the expected hash"),
+		}
+
+		match placeholder_result(
+			"archive",
+			&argument_map(vec![
+				("output", Value::String("a.zip".to_owned())),
+				("files", Value::Array(vec![Value::String("a".to_owned())])),
+			]),
+		) {
+			Some(Value::Array(file_vec)) => assert_eq!(file_vec.len(), 1),
+			_ => panic!("expected an array"),
+		}
+
+		match placeholder_result(
+			"extract",
+			&argument_map(vec![
+				("archive", Value::String("a.zip".to_owned())),
+				("dst", Value::String("a".to_owned())),
+			]),
+		) {
+			Some(Value::Array(file_vec)) => assert!(file_vec.is_empty()),
+			_ => panic!("expected an array"),
+		}
+	}
+
+	#[test]
+	fn returns_the_placeholder_instead_of_running_the_pipeline() {
+		let factory = build_pipeline_map().remove("exec").unwrap();
+		let argument_map = argument_map(vec![("cmd", Value::String("false".to_owned()))]);
+		let result = wrap_builtin_pipeline("exec".to_owned(), factory)(&argument_map)();
+
+		assert!(result.success);
+		assert!(result.result.is_none());
+	}
+}
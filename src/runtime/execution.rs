@@ -1,39 +1,154 @@
 use super::builtins::functions::function::build_function_map;
+use super::execution_log::{ExecutionLog, LogFormat};
 use super::imported_pipeline::ImportedPipeline;
+use super::std_library::is_std_pipeline_path;
 use super::sub_execution::SubExecution;
-use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use super::value::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+// The values exported by an imported pipeline, evaluated the first time one of them is used.
+type ModuleExports = Arc<OnceLock<HashMap<String, Value>>>;
+
+// Writes the trace and prints the summary once the run is over, whether it succeeded or not.
+struct Report<'a>(&'a Execution);
+
+impl Drop for Report<'_> {
+	fn drop(&mut self) {
+		self.0.log.write_trace();
+
+		if self.0.summary {
+			eprintln!("{}", self.0.log.summary());
+		}
+	}
+}
 
 pub struct Execution {
+	dry_run: bool,
+	summary: bool,
+	log: ExecutionLog,
 	imported_pipeline_map: Mutex<HashMap<PathBuf, Arc<ImportedPipeline>>>,
+	module_export_map: Mutex<HashMap<PathBuf, ModuleExports>>,
+	// Pipeline files and patterns passed to glob() during the run, for the watch mode. Pipelines
+	// that failed to import are included, so that fixing them triggers another run.
+	watched_path_set: Mutex<HashSet<PathBuf>>,
+	watched_glob_set: Mutex<HashSet<String>>,
 }
 
 impl Execution {
 	pub fn new() -> Execution {
 		Execution {
+			dry_run: false,
+			summary: false,
+			log: ExecutionLog::new(),
 			imported_pipeline_map: Mutex::new(HashMap::new()),
+			module_export_map: Mutex::new(HashMap::new()),
+			watched_path_set: Mutex::new(HashSet::new()),
+			watched_glob_set: Mutex::new(HashSet::new()),
 		}
 	}
 
+	pub fn dry_run(&self) -> bool {
+		self.dry_run
+	}
+
+	// Evaluates control flow and variables, but only describes the pipelines it would execute.
+	pub fn set_dry_run(&mut self, dry_run: bool) {
+		self.dry_run = dry_run;
+	}
+
+	pub fn log(&self) -> &ExecutionLog {
+		&self.log
+	}
+
+	pub fn set_log_file(&mut self, file: File, format: LogFormat) {
+		self.log.set_writer(file, format);
+	}
+
+	pub fn set_trace_file(&mut self, file: File) {
+		self.log.set_trace_file(file);
+	}
+
+	// Prints a table of all pipeline executions at the end of the run, the slowest first.
+	pub fn set_summary(&mut self, summary: bool) {
+		self.summary = summary;
+	}
+
+	pub fn watch_glob(&self, pattern: String) {
+		self.watched_glob_set.lock().unwrap().insert(pattern);
+	}
+
+	pub fn watched_glob_vec(&self) -> Vec<String> {
+		self.watched_glob_set
+			.lock()
+			.unwrap()
+			.iter()
+			.cloned()
+			.collect()
+	}
+
+	pub fn watched_path_vec(&self) -> Vec<PathBuf> {
+		self.watched_path_set
+			.lock()
+			.unwrap()
+			.iter()
+			.cloned()
+			.collect()
+	}
+
 	pub fn execute(self, entry_pipeline: ImportedPipeline) {
-		let execution = Arc::new(self);
+		Arc::new(self).run(entry_pipeline);
+	}
+
+	// Like execute, but leaves the execution to the caller to inspect it after the run.
+	pub fn run(self: &Arc<Self>, entry_pipeline: ImportedPipeline) {
 		let entry_pipeline = Arc::new(entry_pipeline);
 
-		(*execution.imported_pipeline_map.lock().unwrap())
+		(*self.imported_pipeline_map.lock().unwrap())
 			.insert(entry_pipeline.path().clone(), entry_pipeline.clone());
+		self.watched_path_set
+			.lock()
+			.unwrap()
+			.insert(entry_pipeline.path().clone());
+
+		let _report = Report(self);
+
+		SubExecution::new(self.clone()).execute(&Arc::new(build_function_map()), &entry_pipeline);
+	}
+
+	// Returns a value exported by the given pipeline. The pipeline is evaluated by the given
+	// function only once; later calls, from any thread, wait for and reuse its exports.
+	pub fn module_export(
+		&self,
+		path: &Path,
+		name: &str,
+		evaluate: impl FnOnce() -> HashMap<String, Value>,
+	) -> Option<Value> {
+		let export_map = self
+			.module_export_map
+			.lock()
+			.unwrap()
+			.entry(path.to_path_buf())
+			.or_default()
+			.clone();
 
-		SubExecution::new(execution).execute(&Arc::new(build_function_map()), &entry_pipeline);
+		export_map.get_or_init(evaluate).get(name).cloned()
 	}
 
 	pub fn import(&self, path: PathBuf) -> Result<Arc<ImportedPipeline>, String> {
 		let imported_pipeline_map = &mut *self.imported_pipeline_map.lock().unwrap();
 
-		if imported_pipeline_map.contains_key(&path) {
-			return Err("A pipeline with this name is already exists.".to_owned());
+		if let Some(pipeline) = imported_pipeline_map.get(&path) {
+			return Ok(pipeline.clone());
+		}
+
+		if !is_std_pipeline_path(&path) {
+			self.watched_path_set.lock().unwrap().insert(path.clone());
 		}
 
-		let pipeline = Arc::new(ImportedPipeline::import(&path)?);
+		let pipeline = Arc::new(ImportedPipeline::import(&path).map_err(|err| err.to_string())?);
 		imported_pipeline_map.insert(path, pipeline.clone());
 
 		Ok(pipeline)
@@ -0,0 +1,417 @@
+use super::super::compiler::lexer::Token;
+use super::pipeline::{PipelineExecution, PipelineExecutionResult};
+use super::value::Value;
+use serde_json::json;
+use std::cell::Cell;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Failed pipelines are recorded as their panics unwind through the log.
+#[cfg(panic = "abort")]
+compile_error!("the execution log needs panics to unwind; build with panic = 'unwind'");
+
+thread_local! {
+	// The trace track of the current thread. Each nonblock pipeline runs on a track of its own.
+	static TRACK_ID: Cell<usize> = const { Cell::new(0) };
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogFormat {
+	Text,
+	Json,
+}
+
+impl LogFormat {
+	pub fn from_name(name: &str) -> Option<LogFormat> {
+		match name {
+			"text" => Some(LogFormat::Text),
+			"json" => Some(LogFormat::Json),
+			_ => None,
+		}
+	}
+}
+
+pub enum ExecutionEvent<'a> {
+	PipelineStarted {
+		id: usize,
+		token: &'a Token,
+		argument_map: &'a HashMap<String, Value>,
+	},
+	PipelineFinished {
+		id: usize,
+		token: &'a Token,
+		duration: Duration,
+		success: bool,
+	},
+}
+
+struct PipelineRecord {
+	name: String,
+	location: String,
+	duration: Duration,
+	success: bool,
+}
+
+// Records every pipeline execution of a run. Events are written to the log file as they happen,
+// and finished executions are kept for the summary printed at the end of the run.
+pub struct ExecutionLog {
+	started_at: Instant,
+	next_id: AtomicUsize,
+	writer: Option<(LogFormat, Mutex<BufWriter<File>>)>,
+	record_vec: Mutex<Vec<PipelineRecord>>,
+	next_track_id: AtomicUsize,
+	// Trace events in the Chrome Trace Event format, written to the file once the run is over.
+	trace: Option<(File, Mutex<Vec<serde_json::Value>>)>,
+}
+
+impl ExecutionLog {
+	pub fn new() -> ExecutionLog {
+		ExecutionLog {
+			started_at: Instant::now(),
+			next_id: AtomicUsize::new(0),
+			writer: None,
+			record_vec: Mutex::new(Vec::new()),
+			next_track_id: AtomicUsize::new(1),
+			trace: None,
+		}
+	}
+
+	pub fn set_writer(&mut self, file: File, format: LogFormat) {
+		self.writer = Some((format, Mutex::new(BufWriter::new(file))));
+	}
+
+	pub fn set_trace_file(&mut self, file: File) {
+		self.trace = Some((
+			file,
+			Mutex::new(vec![json!({
+				"name": "thread_name",
+				"ph": "M",
+				"pid": 1,
+				"tid": 0,
+				"args": { "name": "main" },
+			})]),
+		));
+	}
+
+	// Returns a function that moves another thread onto the trace track of the current one.
+	pub fn inherit_track(&self) -> impl FnOnce() + Send {
+		let track_id = TRACK_ID.with(|current| current.get());
+		move || TRACK_ID.with(|current| current.set(track_id))
+	}
+
+	// Moves the current thread onto a new trace track, named after the pipeline it runs.
+	pub fn enter_track(&self, token: &Token) {
+		let track_id = self.next_track_id.fetch_add(1, Ordering::SeqCst);
+		TRACK_ID.with(|current| current.set(track_id));
+
+		if let Some((_, event_vec)) = &self.trace {
+			event_vec.lock().unwrap().push(json!({
+				"name": "thread_name",
+				"ph": "M",
+				"pid": 1,
+				"tid": track_id,
+				"args": {
+					"name": format!(
+						"nonblock #{} {} at {}:{}",
+						track_id, token.token_content, token.file_path, token.line_number
+					),
+				},
+			}));
+		}
+	}
+
+	// Runs the given pipeline execution, emitting an event before and after it. A pipeline that
+	// panics is recorded as failed before the panic continues.
+	pub fn record(
+		&self,
+		token: &Token,
+		argument_map: &HashMap<String, Value>,
+		execution: &mut PipelineExecution,
+	) -> PipelineExecutionResult {
+		let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+
+		self.emit(ExecutionEvent::PipelineStarted {
+			id,
+			token,
+			argument_map,
+		});
+
+		let started_at = Instant::now();
+		let result = catch_unwind(AssertUnwindSafe(execution));
+		let duration = started_at.elapsed();
+		let success = matches!(&result, Ok(result) if result.success);
+
+		if let Some((_, event_vec)) = &self.trace {
+			event_vec.lock().unwrap().push(json!({
+				"name": token.token_content,
+				"cat": "pipeline",
+				"ph": "X",
+				"ts": (started_at - self.started_at).as_micros() as u64,
+				"dur": duration.as_micros() as u64,
+				"pid": 1,
+				"tid": TRACK_ID.with(|current| current.get()),
+				"args": {
+					"file": token.file_path,
+					"line": token.line_number,
+					"arguments": argument_map
+						.iter()
+						.map(|(name, value)| (name.clone(), value_to_json(value)))
+						.collect::<serde_json::Map<_, _>>(),
+					"success": success,
+				},
+			}));
+		}
+
+		self.emit(ExecutionEvent::PipelineFinished {
+			id,
+			token,
+			duration,
+			success,
+		});
+		self.record_vec.lock().unwrap().push(PipelineRecord {
+			name: token.token_content.clone(),
+			location: format!("{}:{}", token.file_path, token.line_number),
+			duration,
+			success,
+		});
+
+		match result {
+			Ok(result) => result,
+			Err(payload) => resume_unwind(payload),
+		}
+	}
+
+	pub fn emit(&self, event: ExecutionEvent) {
+		let (format, writer) = match &self.writer {
+			Some(writer) => writer,
+			None => return,
+		};
+		let time = self.started_at.elapsed().as_secs_f64();
+		let line = match format {
+			LogFormat::Text => match event {
+				ExecutionEvent::PipelineStarted {
+					id,
+					token,
+					argument_map,
+				} => {
+					let mut name_vec = argument_map.keys().collect::<Vec<_>>();
+					name_vec.sort();
+
+					format!(
+						"[{:>10.3}s] #{} started {} at {}:{}{}",
+						time,
+						id,
+						token.token_content,
+						token.file_path,
+						token.line_number,
+						name_vec
+							.into_iter()
+							.map(|name| format!(" {}={}", name, argument_map[name]))
+							.collect::<String>()
+					)
+				}
+				ExecutionEvent::PipelineFinished {
+					id,
+					token,
+					duration,
+					success,
+				} => format!(
+					"[{:>10.3}s] #{} finished {} at {}:{} in {:.3}s; {}",
+					time,
+					id,
+					token.token_content,
+					token.file_path,
+					token.line_number,
+					duration.as_secs_f64(),
+					if success { "succeeded" } else { "failed" }
+				),
+			},
+			LogFormat::Json => match event {
+				ExecutionEvent::PipelineStarted {
+					id,
+					token,
+					argument_map,
+				} => json!({
+					"event": "pipelineStarted",
+					"id": id,
+					"time": time,
+					"pipeline": token.token_content,
+					"file": token.file_path,
+					"line": token.line_number,
+					"arguments": argument_map
+						.iter()
+						.map(|(name, value)| (name.clone(), value_to_json(value)))
+						.collect::<serde_json::Map<_, _>>(),
+				})
+				.to_string(),
+				ExecutionEvent::PipelineFinished {
+					id,
+					token,
+					duration,
+					success,
+				} => json!({
+					"event": "pipelineFinished",
+					"id": id,
+					"time": time,
+					"pipeline": token.token_content,
+					"file": token.file_path,
+					"line": token.line_number,
+					"duration": duration.as_secs_f64(),
+					"success": success,
+				})
+				.to_string(),
+			},
+		};
+
+		let writer = &mut *writer.lock().unwrap();
+
+		if let Err(err) = writeln!(writer, "{}", line).and_then(|_| writer.flush()) {
+			panic!("unable to write the execution log: {}", err);
+		}
+	}
+
+	// Failing to write the trace does not fail the run; it may be written while a failed run unwinds.
+	pub fn write_trace(&self) {
+		if let Some((file, event_vec)) = &self.trace {
+			let trace = json!({
+				"traceEvents": *event_vec.lock().unwrap(),
+				"displayTimeUnit": "ms",
+			});
+
+			if let Err(err) = serde_json::to_writer(BufWriter::new(file), &trace) {
+				eprintln!("Unable to write the trace.");
+				eprintln!("\tbecause: {}", err);
+			}
+		}
+	}
+
+	// Renders the finished executions as a table, the slowest first.
+	pub fn summary(&self) -> String {
+		let mut record_vec = self.record_vec.lock().unwrap();
+		record_vec.sort_by_key(|record| Reverse(record.duration));
+
+		let row_vec = record_vec
+			.iter()
+			.map(|record| {
+				[
+					record.name.clone(),
+					record.location.clone(),
+					format!("{:.3}s", record.duration.as_secs_f64()),
+					if record.success { "ok" } else { "failed" }.to_owned(),
+				]
+			})
+			.collect::<Vec<_>>();
+		let header = [
+			"Pipeline".to_owned(),
+			"Location".to_owned(),
+			"Duration".to_owned(),
+			"Result".to_owned(),
+		];
+		let mut width_vec = header.iter().map(|column| column.len()).collect::<Vec<_>>();
+
+		for row in row_vec.iter() {
+			for (index, column) in row.iter().enumerate() {
+				width_vec[index] = width_vec[index].max(column.chars().count());
+			}
+		}
+
+		let mut summary = String::new();
+
+		for row in Some(&header).into_iter().chain(row_vec.iter()) {
+			let line = row
+				.iter()
+				.zip(width_vec.iter())
+				.map(|(column, width)| format!("{:<width$}", column, width = width))
+				.collect::<Vec<_>>()
+				.join("  ");
+
+			summary += line.trim_end();
+			summary += "\n";
+		}
+
+		summary += &format!(
+			"{} pipeline execution{} in {:.3}s.",
+			record_vec.len(),
+			if record_vec.len() == 1 { "" } else { "s" },
+			self.started_at.elapsed().as_secs_f64()
+		);
+		summary
+	}
+}
+
+fn value_to_json(value: &Value) -> serde_json::Value {
+	match value {
+		Value::Array(array) => array.iter().map(value_to_json).collect(),
+		Value::Dictionary(dictionary) => serde_json::Value::Object(
+			dictionary
+				.iter()
+				.map(|(key, value)| (key.clone(), value_to_json(value)))
+				.collect(),
+		),
+		Value::Bool(bool_value) => json!(bool_value),
+		Value::Integer(integer_value) => json!(integer_value),
+		Value::String(string_value) => json!(string_value),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::super::compiler::lexer::TokenType;
+	use super::*;
+
+	fn record_panic(log: &ExecutionLog) {
+		let token = Token {
+			file_path: "test.piped".to_owned(),
+			token_type: TokenType::Id,
+			token_content: "extract".to_owned(),
+			line_number: 3,
+			line_offset: 1,
+		};
+		let mut execution = || -> PipelineExecutionResult { panic!("unable to extract") };
+
+		assert!(catch_unwind(AssertUnwindSafe(|| {
+			log.record(&token, &HashMap::new(), &mut execution)
+		}))
+		.is_err());
+	}
+
+	#[test]
+	fn records_a_panicking_pipeline_as_failed() {
+		let log = ExecutionLog::new();
+		record_panic(&log);
+
+		let summary = log.summary();
+
+		assert!(summary.contains("extract   test.piped:3"));
+		assert!(summary.contains("failed"));
+		assert!(summary.contains("1 pipeline execution in "));
+	}
+
+	#[test]
+	fn traces_a_panicking_pipeline_as_failed() {
+		let path = std::env::temp_dir().join(format!("piped-trace-{}.json", std::process::id()));
+		let mut log = ExecutionLog::new();
+		log.set_trace_file(File::create(&path).unwrap());
+		record_panic(&log);
+		log.write_trace();
+
+		let trace: serde_json::Value =
+			serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+		let _ = std::fs::remove_file(&path);
+		let event = trace["traceEvents"]
+			.as_array()
+			.unwrap()
+			.last()
+			.unwrap()
+			.clone();
+
+		assert_eq!(event["name"], "extract");
+		assert_eq!(event["args"]["success"], false);
+	}
+}
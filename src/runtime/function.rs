@@ -2,5 +2,7 @@ use super::sub_execution::SubExecution;
 use super::value::Value;
 
 pub trait Function {
+    // The number of arguments this function takes; None if it takes any number of them.
+    fn argument_count(&self) -> Option<usize>;
     fn call(&self, sub_execution: &mut SubExecution, argument_vec: Vec<Value>) -> Value;
 }
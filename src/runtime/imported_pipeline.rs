@@ -1,36 +1,201 @@
+use super::super::compiler::diagnostic::Diagnostic;
 use super::super::compiler::lookahead_lexer::LookaheadLexer as Lexer;
 use super::super::compiler::parser::{parse, AST};
+use super::pipeline::PipelineParameter;
+use super::project_config::ProjectConfig;
+use super::std_library::{is_std_pipeline_path, std_pipeline_path, std_pipeline_source};
+use super::value::ValueType;
+use path_absolutize::Absolutize;
+use regex::Regex;
+use std::env::{split_paths, var_os};
+use std::fmt::{Display, Formatter};
 use std::fs::read_to_string;
-use std::path::{Path, PathBuf};
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
 
 pub struct ImportedPipeline {
 	path: PathBuf,
+	src_content: Vec<String>,
 	ast_vec: Vec<AST>,
+	parameter_vec: Vec<PipelineParameter>,
+}
+
+pub enum ImportError {
+	Unreadable(String),
+	Malformed(Vec<Diagnostic>, Vec<String>),
+}
+
+impl Display for ImportError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ImportError::Unreadable(reason) => write!(f, "{}", reason),
+			ImportError::Malformed(diagnostic_vec, src_content) => {
+				for diagnostic in diagnostic_vec.iter() {
+					writeln!(f, "{}", diagnostic.render(src_content))?;
+				}
+
+				write!(
+					f,
+					"Unable to proceed due to {} previous error{}.",
+					diagnostic_vec.len(),
+					if diagnostic_vec.len() == 1 { "" } else { "s" }
+				)
+			}
+		}
+	}
 }
 
 impl ImportedPipeline {
-	pub fn import(path: &Path) -> Result<ImportedPipeline, String> {
-		let canonicalized_path = path.canonicalize().map_err(|err| format!("{}", err))?;
+	pub fn import(path: &Path) -> Result<ImportedPipeline, ImportError> {
+		if is_std_pipeline_path(path) {
+			let src_content = std_pipeline_source(path).ok_or_else(|| {
+				ImportError::Unreadable(
+					"There is no such pipeline in the standard library.".to_owned(),
+				)
+			})?;
+
+			return ImportedPipeline::parse(path.to_path_buf(), src_content.to_owned());
+		}
+
+		let canonicalized_path = path
+			.canonicalize()
+			.map_err(|err| ImportError::Unreadable(format!("{}", err)))?;
+		let src_content = read_to_string(canonicalized_path.clone())
+			.map_err(|err| ImportError::Unreadable(format!("{}", err)))?;
+
+		ImportedPipeline::parse(canonicalized_path, src_content)
+	}
+
+	fn parse(path: PathBuf, src_content: String) -> Result<ImportedPipeline, ImportError> {
 		let mut lexer = Lexer::new(
-			canonicalized_path
-				.to_str()
-				.ok_or("Unable to proceed due to the previous error.".to_owned())?
+			path.to_str()
+				.ok_or_else(|| ImportError::Unreadable("The path is not valid UTF-8.".to_owned()))?
 				.to_owned(),
-			read_to_string(canonicalized_path.clone()).map_err(|err| format!("{}", err))?,
+			src_content,
 		);
+		let ast_vec = parse(&mut lexer).map_err(|diagnostic_vec| {
+			ImportError::Malformed(diagnostic_vec, lexer.src_content().clone())
+		})?;
 
-		Ok(ImportedPipeline {
-			path: canonicalized_path,
-			ast_vec: parse(&mut lexer)
-				.map_err(|_| "Unable to proceed due to the previous error.".to_owned())?,
-		})
+		Ok(ImportedPipeline::new(
+			path,
+			lexer.src_content().clone(),
+			ast_vec,
+		))
+	}
+
+	pub fn new(path: PathBuf, src_content: Vec<String>, ast_vec: Vec<AST>) -> ImportedPipeline {
+		let parameter_vec = ast_vec
+			.iter()
+			.filter_map(|ast| match ast {
+				AST::Param(param_ast) => Some(PipelineParameter {
+					name: param_ast.name.token_content.clone(),
+					value_type: ValueType::from_name(&param_ast.value_type.token_content).unwrap(),
+					required: param_ast.default.is_none(),
+				}),
+				_ => None,
+			})
+			.collect();
+
+		ImportedPipeline {
+			path,
+			src_content,
+			ast_vec,
+			parameter_vec,
+		}
 	}
 
 	pub fn path(&self) -> &PathBuf {
 		&self.path
 	}
 
+	pub fn src_content(&self) -> &Vec<String> {
+		&self.src_content
+	}
+
 	pub fn ast_vec(&self) -> &Vec<AST> {
 		&self.ast_vec
 	}
+
+	pub fn parameter_vec(&self) -> &Vec<PipelineParameter> {
+		&self.parameter_vec
+	}
+}
+
+// Resolves the path of an @import statement. Paths starting with ./ or ../ and absolute paths are
+// relative to the importing pipeline. Other paths are looked up next to the importing pipeline
+// first, then in the import paths of the project config, its vendored dependencies and PIPED_PATH,
+// with or without the .piped extension; paths under std/ that match no such file refer to the
+// standard library embedded in the binary.
+pub fn resolve_import_path(importer_path: &Path, path: &str) -> Result<PathBuf, String> {
+	let path = Regex::new("[/\\\\]")
+		.unwrap()
+		.replace_all(path, &*MAIN_SEPARATOR.to_string())
+		.into_owned();
+	let path = Path::new(&path);
+	let mut base_path = importer_path.to_path_buf();
+	base_path.pop();
+
+	let relative_path = PathBuf::from(base_path.join(path).absolutize().unwrap());
+
+	if path.is_absolute() || path.starts_with(".") || path.starts_with("..") {
+		return Ok(relative_path);
+	}
+
+	let mut search_path_vec = vec![base_path.clone()];
+
+	if let Some(project_config) = ProjectConfig::find(&base_path)? {
+		search_path_vec.extend(project_config.import_path_vec().iter().cloned());
+	}
+
+	if let Some(piped_path) = var_os("PIPED_PATH") {
+		search_path_vec.extend(split_paths(&piped_path));
+	}
+
+	for search_path in search_path_vec.iter() {
+		let candidate = search_path.join(path);
+		// A directory, e.g. a package vendored by piped fetch, is imported through its lib.piped.
+		let candidate_vec = if candidate.extension().is_none() {
+			vec![
+				candidate.clone(),
+				candidate.with_extension("piped"),
+				candidate.join("lib.piped"),
+			]
+		} else {
+			vec![candidate]
+		};
+
+		for candidate in candidate_vec {
+			if candidate.is_file() {
+				return Ok(PathBuf::from(candidate.absolutize().unwrap()));
+			}
+		}
+	}
+
+	if let Ok(name) = path.strip_prefix("std") {
+		return Ok(std_pipeline_path(&name.to_string_lossy()));
+	}
+
+	Ok(relative_path)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::fs::{create_dir_all, remove_dir_all, write};
+	use std::process;
+
+	#[test]
+	fn prefers_a_local_std_directory_over_the_standard_library() {
+		let directory = std::env::temp_dir().join(format!("piped-import-std-{}", process::id()));
+		create_dir_all(directory.join("std")).unwrap();
+		write(directory.join("std").join("cargo.piped"), "").unwrap();
+
+		let importer_path = directory.join("main.piped");
+		let local_path = resolve_import_path(&importer_path, "std/cargo");
+		let std_path = resolve_import_path(&importer_path, "std/git");
+		remove_dir_all(&directory).unwrap();
+
+		assert_eq!(local_path, Ok(directory.join("std").join("cargo.piped")));
+		assert_eq!(std_path, Ok(std_pipeline_path("git")));
+	}
 }
@@ -1,7 +1,20 @@
+pub mod action_cache;
 pub mod builtins;
+pub mod cancellation;
+pub mod dry_run;
 pub mod execution;
+pub mod execution_log;
 pub mod function;
 pub mod imported_pipeline;
+pub mod modifier;
+pub mod output;
 pub mod pipeline;
+pub mod project_config;
+pub mod remote_cache;
+pub mod repl;
+pub mod signal;
+pub mod std_library;
 pub mod sub_execution;
+pub mod test_runner;
 pub mod value;
+pub mod watch;
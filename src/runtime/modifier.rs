@@ -0,0 +1,179 @@
+use super::cancellation::{current_cancellation, enter_cancellation};
+use super::execution::Execution;
+use super::output::inherit_job;
+use super::pipeline::{PipelineExecution, PipelineExecutionResult};
+use std::any::Any;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::{sleep, spawn};
+use std::time::Duration;
+
+// Failed and timed out attempts are retried once their panics are caught.
+#[cfg(panic = "abort")]
+compile_error!(
+	"the @retry and @timeout modifiers need panics to unwind; build with panic = 'unwind'"
+);
+
+enum Attempt {
+	Finished(PipelineExecutionResult),
+	Panicked(Box<dyn Any + Send>),
+	TimedOut,
+}
+
+// Wraps the executions of a pipeline, one per attempt, into one that tries them in turn until
+// one succeeds, waiting 1, 2, 4, ... seconds in between. Each attempt is created by the given
+// function right before it runs. An attempt fails if its pipeline fails, panics or takes longer
+// than the timeout, and the failure of the last attempt fails the whole, whether it runs in the
+// background or not.
+pub fn with_modifiers(
+	execution: Arc<Execution>,
+	label: String,
+	attempt_count: usize,
+	mut create_attempt: impl FnMut() -> Box<PipelineExecution> + Send + 'static,
+	timeout: Option<Duration>,
+) -> Box<PipelineExecution> {
+	Box::new(move || -> PipelineExecutionResult {
+		for index in 0..attempt_count {
+			let attempt = run_attempt(&execution, create_attempt(), timeout);
+			// A cancelled pipeline, e.g. because another job failed, is not worth retrying.
+			let is_last = index + 1 == attempt_count || current_cancellation().is_cancelled();
+			let reason = match attempt {
+				Attempt::Finished(result) if result.success => return result,
+				Attempt::Finished(..) if is_last => panic!("{} failed", label),
+				Attempt::Finished(..) => "it failed".to_owned(),
+				Attempt::Panicked(payload) if is_last => resume_unwind(payload),
+				Attempt::Panicked(payload) => panic_message(&*payload),
+				Attempt::TimedOut if is_last => {
+					panic!("{} timed out after {}s", label, timeout.unwrap().as_secs())
+				}
+				Attempt::TimedOut => format!("it timed out after {}s", timeout.unwrap().as_secs()),
+			};
+
+			eprintln!("Retrying {} ({}/{}).", label, index + 1, attempt_count - 1);
+			eprintln!("\tbecause: {}", reason);
+			sleep(Duration::from_secs(1 << index.min(5)));
+		}
+
+		unreachable!("the last attempt always returns or panics")
+	})
+}
+
+// Runs the attempt on a thread of its own if it has a timeout, so that it can be given up on.
+// Commands of a timed out attempt are killed; other work it does is left to finish on its own.
+fn run_attempt(
+	execution: &Arc<Execution>,
+	attempt: Box<PipelineExecution>,
+	timeout: Option<Duration>,
+) -> Attempt {
+	let timeout = match timeout {
+		Some(timeout) => timeout,
+		None => {
+			return match catch_unwind(AssertUnwindSafe(attempt)) {
+				Ok(result) => Attempt::Finished(result),
+				Err(payload) => Attempt::Panicked(payload),
+			}
+		}
+	};
+
+	let cancellation = current_cancellation().child();
+	let (sender, receiver) = channel();
+	let inherit_job = inherit_job();
+	let inherit_track = execution.log().inherit_track();
+
+	{
+		let cancellation = cancellation.clone();
+
+		spawn(move || {
+			inherit_job();
+			inherit_track();
+			enter_cancellation(cancellation);
+
+			let _ = sender.send(catch_unwind(AssertUnwindSafe(attempt)));
+		});
+	}
+
+	match receiver.recv_timeout(timeout) {
+		Ok(Ok(result)) => Attempt::Finished(result),
+		Ok(Err(payload)) => Attempt::Panicked(payload),
+		Err(RecvTimeoutError::Timeout) => {
+			cancellation.cancel();
+
+			// Give the attempt a moment to wind down once its commands are killed.
+			let _ = receiver.recv_timeout(Duration::from_secs(5));
+			Attempt::TimedOut
+		}
+		Err(RecvTimeoutError::Disconnected) => unreachable!(),
+	}
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+	match payload.downcast_ref::<&str>() {
+		Some(message) => message.to_string(),
+		None => match payload.downcast_ref::<String>() {
+			Some(message) => message.clone(),
+			None => "unknown error".to_owned(),
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	#[test]
+	fn creates_attempts_only_once_the_previous_one_failed() {
+		let created = Arc::new(AtomicUsize::new(0));
+		let mut execution = {
+			let created = created.clone();
+
+			with_modifiers(
+				Arc::new(Execution::new()),
+				"'flaky'".to_owned(),
+				5,
+				move || -> Box<PipelineExecution> {
+					let index = created.fetch_add(1, Ordering::SeqCst);
+
+					Box::new(move || PipelineExecutionResult {
+						success: index == 1,
+						result: None,
+					})
+				},
+				None,
+			)
+		};
+
+		assert!(execution().success);
+		assert_eq!(created.load(Ordering::SeqCst), 2);
+	}
+
+	#[test]
+	fn fails_once_the_last_attempt_failed() {
+		let created = Arc::new(AtomicUsize::new(0));
+		let mut execution = {
+			let created = created.clone();
+
+			with_modifiers(
+				Arc::new(Execution::new()),
+				"'broken'".to_owned(),
+				2,
+				move || -> Box<PipelineExecution> {
+					created.fetch_add(1, Ordering::SeqCst);
+
+					Box::new(|| PipelineExecutionResult {
+						success: false,
+						result: None,
+					})
+				},
+				None,
+			)
+		};
+
+		match catch_unwind(AssertUnwindSafe(execution)) {
+			Ok(..) => panic!("expected the last failed attempt to panic"),
+			Err(payload) => assert_eq!(panic_message(&*payload), "'broken' failed"),
+		}
+		assert_eq!(created.load(Ordering::SeqCst), 2);
+	}
+}
@@ -0,0 +1,303 @@
+use super::cancellation::{current_cancellation, CancellationToken};
+use super::signal::{kill, park_if_interrupted, release_child, track_child};
+use std::cell::RefCell;
+use std::io::{stderr, stdin, stdout, BufRead, BufReader, IsTerminal, Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{sleep, spawn, JoinHandle};
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputMode {
+	// Child processes write to the terminal directly.
+	Inherit,
+	// Every line a child process writes is prefixed with the job it belongs to.
+	Prefixed,
+	// Like prefixed, but the lines are held back until the child process exits.
+	Buffered,
+}
+
+impl OutputMode {
+	pub fn from_name(name: &str) -> Option<OutputMode> {
+		match name {
+			"inherit" => Some(OutputMode::Inherit),
+			"prefixed" => Some(OutputMode::Prefixed),
+			"buffered" => Some(OutputMode::Buffered),
+			_ => None,
+		}
+	}
+}
+
+struct OutputOptions {
+	mode: OutputMode,
+	colored: bool,
+}
+
+#[derive(Clone)]
+struct Job {
+	name: Option<String>,
+	color_index: usize,
+}
+
+#[derive(Clone, Copy)]
+enum Stream {
+	Stdout,
+	Stderr,
+}
+
+type OutputBuffer = Arc<Mutex<Vec<(Stream, Vec<u8>)>>>;
+type LineWriter = fn(Stream, &[u8]);
+
+const COLORS: [u8; 6] = [36, 33, 35, 32, 34, 31];
+
+// Pipelines have no access to the execution, so the output options are process-wide.
+static OUTPUT_OPTIONS: Mutex<OutputOptions> = Mutex::new(OutputOptions {
+	mode: OutputMode::Inherit,
+	colored: false,
+});
+// Held while writing, so that lines and buffered outputs of different jobs never interleave.
+static OUTPUT_LOCK: Mutex<()> = Mutex::new(());
+static NEXT_COLOR_INDEX: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+	// The nonblock pipeline the current thread runs, if any.
+	static JOB: RefCell<Option<Job>> = const { RefCell::new(None) };
+}
+
+pub fn set_output_options(mode: OutputMode, colored: bool) {
+	*OUTPUT_OPTIONS.lock().unwrap() = OutputOptions { mode, colored };
+}
+
+// Marks the current thread as running a nonblock pipeline with the given name.
+pub fn enter_job(name: Option<String>) {
+	let color_index = NEXT_COLOR_INDEX.fetch_add(1, Ordering::SeqCst);
+	JOB.with(|job| *job.borrow_mut() = Some(Job { name, color_index }));
+}
+
+// Returns a function that makes another thread run as part of the same job as the current one.
+pub fn inherit_job() -> impl FnOnce() + Send {
+	let current = JOB.with(|job| job.borrow().clone());
+	move || JOB.with(|job| *job.borrow_mut() = current)
+}
+
+// Runs the command to completion and returns whether it succeeded. Its output is forwarded
+// according to the output options; the command name is used as a prefix for unnamed jobs.
+pub fn run_command(command: &mut Command, command_name: &str) -> bool {
+	let (mode, colored) = {
+		let options = OUTPUT_OPTIONS.lock().unwrap();
+		(options.mode, options.colored)
+	};
+
+	run_command_with(command, command_name, mode, colored, write_line)
+}
+
+fn run_command_with(
+	command: &mut Command,
+	command_name: &str,
+	mode: OutputMode,
+	colored: bool,
+	write_line: LineWriter,
+) -> bool {
+	// Commands get a process group of their own, so that the processes they start are stopped
+	// along with them. Commands that can not be cancelled on their own stay in the foreground
+	// process group of a terminal instead, to keep reading from it and to get Ctrl-C from it.
+	let cancellation = current_cancellation();
+	let has_process_group = !cancellation.is_root() || !stdin().is_terminal();
+
+	#[cfg(unix)]
+	{
+		if has_process_group {
+			use std::os::unix::process::CommandExt;
+
+			command.process_group(0);
+		}
+	}
+
+	park_if_interrupted();
+
+	if mode == OutputMode::Inherit {
+		let success = match command.spawn() {
+			Ok(child) => wait(child, &cancellation, has_process_group),
+			Err(..) => false,
+		};
+
+		park_if_interrupted();
+		return success;
+	}
+
+	let mut child = match command
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+	{
+		Ok(child) => child,
+		Err(..) => return false,
+	};
+	let prefix = JOB.with(|job| match &*job.borrow() {
+		Some(job) => make_prefix(
+			job.name.as_deref().unwrap_or(command_name),
+			if colored { Some(job.color_index) } else { None },
+		),
+		None => make_prefix(command_name, None),
+	});
+	let buffer = if mode == OutputMode::Buffered {
+		Some(Arc::new(Mutex::new(Vec::new())))
+	} else {
+		None
+	};
+	let stdout_forwarder = forward(
+		child.stdout.take().unwrap(),
+		Stream::Stdout,
+		prefix.clone(),
+		buffer.clone(),
+		write_line,
+	);
+	let stderr_forwarder = forward(
+		child.stderr.take().unwrap(),
+		Stream::Stderr,
+		prefix,
+		buffer.clone(),
+		write_line,
+	);
+
+	let success = wait(child, &cancellation, has_process_group);
+	park_if_interrupted();
+
+	stdout_forwarder.join().unwrap();
+	stderr_forwarder.join().unwrap();
+
+	if let Some(buffer) = buffer {
+		let _lock = OUTPUT_LOCK.lock().unwrap();
+
+		for (stream, line) in buffer.lock().unwrap().iter() {
+			write_line(*stream, line);
+		}
+	}
+
+	success
+}
+
+// Waits for the child to exit and returns whether it succeeded. The child is killed as soon as
+// the pipeline it runs for is cancelled, e.g. when it timed out or a sibling job failed.
+fn wait(mut child: Child, cancellation: &CancellationToken, has_process_group: bool) -> bool {
+	track_child(child.id(), has_process_group);
+
+	let success = loop {
+		match child.try_wait() {
+			Ok(Some(status)) => break status.success(),
+			Ok(None) => {}
+			Err(..) => break false,
+		}
+
+		if cancellation.is_cancelled() {
+			kill(&mut child, has_process_group);
+			let _ = child.wait();
+			break false;
+		}
+
+		sleep(Duration::from_millis(20));
+	};
+
+	release_child(child.id());
+	success
+}
+
+fn make_prefix(name: &str, color_index: Option<usize>) -> String {
+	match color_index {
+		Some(color_index) => format!(
+			"\x1b[{}m[{}]\x1b[0m ",
+			COLORS[color_index % COLORS.len()],
+			name
+		),
+		None => format!("[{}] ", name),
+	}
+}
+
+fn forward<R: Read + Send + 'static>(
+	reader: R,
+	stream: Stream,
+	prefix: String,
+	buffer: Option<OutputBuffer>,
+	write_line: LineWriter,
+) -> JoinHandle<()> {
+	spawn(move || {
+		let mut reader = BufReader::new(reader);
+
+		loop {
+			let mut line = prefix.clone().into_bytes();
+
+			match reader.read_until(b'\n', &mut line) {
+				Ok(0) | Err(..) => break,
+				Ok(..) => {}
+			}
+
+			if line.last() != Some(&b'\n') {
+				line.push(b'\n');
+			}
+
+			match &buffer {
+				Some(buffer) => buffer.lock().unwrap().push((stream, line)),
+				None => {
+					let _lock = OUTPUT_LOCK.lock().unwrap();
+					write_line(stream, &line);
+				}
+			}
+		}
+	})
+}
+
+fn write_line(stream: Stream, line: &[u8]) {
+	// A closed terminal or pipe must not fail the pipeline that produced the output.
+	let _ = match stream {
+		Stream::Stdout => stdout().lock().write_all(line),
+		Stream::Stderr => stderr().lock().write_all(line),
+	};
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	static WRITTEN_LINES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+	fn record_line(_stream: Stream, line: &[u8]) {
+		WRITTEN_LINES
+			.lock()
+			.unwrap()
+			.push(String::from_utf8_lossy(line).into_owned());
+	}
+
+	#[test]
+	fn buffered_mode_keeps_the_lines_of_a_job_together() {
+		let job_vec: Vec<_> = ["a", "b"]
+			.iter()
+			.map(|name| {
+				spawn(move || {
+					enter_job(Some(name.to_string()));
+
+					let mut command = Command::new("sh");
+					command
+						.arg("-c")
+						.arg("echo one; sleep 0.2; echo two; printf partial");
+					run_command_with(&mut command, "sh", OutputMode::Buffered, false, record_line)
+				})
+			})
+			.collect();
+
+		for job in job_vec {
+			assert!(job.join().unwrap());
+		}
+
+		let line_vec = WRITTEN_LINES.lock().unwrap().clone();
+		let expected_a = ["[a] one\n", "[a] two\n", "[a] partial\n"];
+		let expected_b = ["[b] one\n", "[b] two\n", "[b] partial\n"];
+
+		assert!(
+			line_vec == [expected_a, expected_b].concat()
+				|| line_vec == [expected_b, expected_a].concat(),
+			"{:?}",
+			line_vec
+		);
+	}
+}
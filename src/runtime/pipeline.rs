@@ -1,10 +1,64 @@
-use super::value::Value;
+use super::value::{Value, ValueType};
 use std::collections::HashMap;
 
-pub type PipelineFactory = dyn Fn(&HashMap<String, Value>) -> Box<PipelineExecution>;
+pub type PipelineFactory = dyn Fn(&HashMap<String, Value>) -> Box<PipelineExecution> + Send + Sync;
 pub type PipelineExecution = dyn FnMut() -> PipelineExecutionResult + Send;
 
 pub struct PipelineExecutionResult {
 	pub success: bool,
 	pub result: Option<Value>,
 }
+
+#[derive(Clone, Debug)]
+pub struct PipelineParameter {
+	pub name: String,
+	pub value_type: ValueType,
+	pub required: bool,
+}
+
+pub fn validate_argument_map(
+	parameter_vec: &[PipelineParameter],
+	argument_map: &HashMap<String, Value>,
+) -> Result<(), String> {
+	let mut name_vec = argument_map.keys().collect::<Vec<_>>();
+	name_vec.sort();
+
+	for name in name_vec {
+		if !parameter_vec
+			.iter()
+			.any(|parameter| &parameter.name == name)
+		{
+			return Err(format!(
+				"unknown argument '{}'; expected one of {}",
+				name,
+				parameter_vec
+					.iter()
+					.map(|parameter| format!("'{}'", parameter.name))
+					.collect::<Vec<_>>()
+					.join(", ")
+			));
+		}
+	}
+
+	for parameter in parameter_vec.iter() {
+		match argument_map.get(&parameter.name) {
+			Some(value) => {
+				if value.value_type() != parameter.value_type {
+					return Err(format!(
+						"'{}' must be a '{:#?}' type, got '{:#?}'",
+						parameter.name,
+						parameter.value_type,
+						value.value_type()
+					));
+				}
+			}
+			None => {
+				if parameter.required {
+					return Err(format!("'{}' is required", parameter.name));
+				}
+			}
+		}
+	}
+
+	Ok(())
+}
@@ -0,0 +1,262 @@
+use path_absolutize::Absolutize;
+use std::env::var_os;
+use std::fs::read_to_string;
+use std::path::{Component, Path, PathBuf};
+use toml::Value;
+
+pub const PROJECT_CONFIG_FILE_NAME: &str = "piped.toml";
+
+// The piped.toml of a project, found in the directory of a pipeline or any of its ancestors.
+pub struct ProjectConfig {
+	directory: PathBuf,
+	import_path_vec: Vec<PathBuf>,
+	dependency_vec: Vec<Dependency>,
+}
+
+pub struct Dependency {
+	pub name: String,
+	pub source: DependencySource,
+}
+
+pub enum DependencySource {
+	// A version of the package in a local registry, stored as <registry>/<name>/<version>.
+	// The registry is None if neither the dependency nor the project configures one.
+	Registry {
+		registry: Option<PathBuf>,
+		version: String,
+	},
+	// A directory somewhere on disk, e.g. a library shared in a monorepo.
+	Path(PathBuf),
+}
+
+impl DependencySource {
+	pub fn directory(&self, name: &str) -> Result<PathBuf, String> {
+		match self {
+			DependencySource::Registry {
+				registry: Some(registry),
+				version,
+			} => Ok(registry.join(name).join(version)),
+			DependencySource::Registry { registry: None, .. } => Err(format!(
+				"no registry is configured for '{}'; set 'registry.path' or PIPED_REGISTRY",
+				name
+			)),
+			DependencySource::Path(path) => Ok(path.clone()),
+		}
+	}
+}
+
+impl ProjectConfig {
+	pub fn find(directory: &Path) -> Result<Option<ProjectConfig>, String> {
+		for directory in directory.ancestors() {
+			let path = directory.join(PROJECT_CONFIG_FILE_NAME);
+
+			if path.is_file() {
+				return ProjectConfig::load(&path).map(Some);
+			}
+		}
+
+		Ok(None)
+	}
+
+	pub fn load(path: &Path) -> Result<ProjectConfig, String> {
+		let invalid =
+			|reason: String| format!("invalid project config '{}': {}", path.display(), reason);
+		let content = read_to_string(path).map_err(|err| invalid(err.to_string()))?;
+		let config = content
+			.parse::<Value>()
+			.map_err(|err| invalid(err.to_string()))?;
+		let mut directory = path.to_path_buf();
+		directory.pop();
+
+		let mut import_path_vec = match config.get("import").and_then(|import| import.get("paths"))
+		{
+			Some(Value::Array(path_vec)) => path_vec
+				.iter()
+				.map(|import_path| match import_path {
+					Value::String(import_path) => Ok(directory.join(import_path)),
+					_ => Err(invalid(
+						"'import.paths' must contain strings only".to_owned(),
+					)),
+				})
+				.collect::<Result<Vec<_>, _>>()?,
+			Some(..) => return Err(invalid("'import.paths' must be an array".to_owned())),
+			None => Vec::new(),
+		};
+		import_path_vec.push(dependency_directory(&directory));
+
+		let default_registry = match config
+			.get("registry")
+			.and_then(|registry| registry.get("path"))
+		{
+			Some(Value::String(registry)) => Some(absolute_path(&directory, registry)),
+			Some(..) => return Err(invalid("'registry.path' must be a string".to_owned())),
+			None => var_os("PIPED_REGISTRY").map(PathBuf::from),
+		};
+		let mut dependency_vec = Vec::new();
+
+		match config.get("dependencies") {
+			Some(Value::Table(dependency_table)) => {
+				for (name, spec) in dependency_table.iter() {
+					let source = match spec {
+						Value::String(version) => DependencySource::Registry {
+							registry: default_registry.clone(),
+							version: version.clone(),
+						},
+						Value::Table(spec) => match (spec.get("path"), spec.get("version")) {
+							(Some(Value::String(path)), None) => {
+								DependencySource::Path(absolute_path(&directory, path))
+							}
+							(None, Some(Value::String(version))) => DependencySource::Registry {
+								registry: match spec.get("registry") {
+									Some(Value::String(registry)) => {
+										Some(absolute_path(&directory, registry))
+									}
+									Some(..) => {
+										return Err(invalid(format!(
+											"the registry of '{}' must be a string",
+											name
+										)))
+									}
+									None => default_registry.clone(),
+								},
+								version: version.clone(),
+							},
+							_ => {
+								return Err(invalid(format!(
+									"'{}' must have either a 'path' or a 'version' string",
+									name
+								)))
+							}
+						},
+						_ => {
+							return Err(invalid(format!(
+								"'{}' must be a version string or a table",
+								name
+							)))
+						}
+					};
+
+					// Names and versions become directories of the registry and the project.
+					if !is_path_component(name) {
+						return Err(invalid(format!(
+							"the package name '{}' must not contain path separators or be '.' or '..'",
+							name
+						)));
+					}
+
+					if let DependencySource::Registry { version, .. } = &source {
+						if !is_path_component(version) {
+							return Err(invalid(format!(
+								"the version '{}' of '{}' must not contain path separators or be '.' or '..'",
+								version, name
+							)));
+						}
+					}
+
+					dependency_vec.push(Dependency {
+						name: name.clone(),
+						source,
+					});
+				}
+			}
+			Some(..) => return Err(invalid("'dependencies' must be a table".to_owned())),
+			None => {}
+		}
+
+		Ok(ProjectConfig {
+			directory,
+			import_path_vec,
+			dependency_vec,
+		})
+	}
+
+	// The directory the config is in, i.e. the root of the project.
+	pub fn directory(&self) -> &PathBuf {
+		&self.directory
+	}
+
+	// Directories searched for pipelines imported by a bare name, relative to the config. The
+	// directory dependencies are vendored into is always searched last.
+	pub fn import_path_vec(&self) -> &Vec<PathBuf> {
+		&self.import_path_vec
+	}
+
+	pub fn dependency_vec(&self) -> &Vec<Dependency> {
+		&self.dependency_vec
+	}
+
+	pub fn dependency_directory(&self) -> PathBuf {
+		dependency_directory(&self.directory)
+	}
+}
+
+// Dependencies are vendored into this directory of the project.
+fn dependency_directory(project_directory: &Path) -> PathBuf {
+	project_directory.join(".piped").join("deps")
+}
+
+fn absolute_path(directory: &Path, path: &str) -> PathBuf {
+	PathBuf::from(directory.join(path).absolutize().unwrap())
+}
+
+fn is_path_component(value: &str) -> bool {
+	!value.contains(['/', '\\'])
+		&& matches!(
+			Path::new(value).components().collect::<Vec<_>>().as_slice(),
+			[Component::Normal(component)] if *component == value
+		)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::fs::{create_dir_all, remove_dir_all, write};
+	use std::process;
+
+	fn load(name: &str, dependencies: &str) -> Result<ProjectConfig, String> {
+		let directory =
+			std::env::temp_dir().join(format!("piped-config-{}-{}", name, process::id()));
+		create_dir_all(&directory).unwrap();
+		write(
+			directory.join(PROJECT_CONFIG_FILE_NAME),
+			format!(
+				"[registry]\npath = \"registry\"\n\n[dependencies]\n{}\n",
+				dependencies
+			),
+		)
+		.unwrap();
+
+		let result = ProjectConfig::load(&directory.join(PROJECT_CONFIG_FILE_NAME));
+		remove_dir_all(&directory).unwrap();
+		result
+	}
+
+	#[test]
+	fn accepts_plain_names_and_versions() {
+		let config = load(
+			"plain",
+			"deploy = \"0.3.0\"\nshared = { path = \"../shared\" }",
+		)
+		.unwrap();
+
+		assert_eq!(config.dependency_vec().len(), 2);
+	}
+
+	#[test]
+	fn rejects_names_and_versions_that_leave_the_registry() {
+		for dependencies in [
+			"\"../deploy\" = \"0.3.0\"",
+			"\"..\" = \"0.3.0\"",
+			"\"a/b\" = { path = \"../shared\" }",
+			"deploy = \"../../etc\"",
+			"deploy = \"/etc\"",
+			"deploy = \"0.3.0\\\\..\"",
+			"deploy = { version = \".\" }",
+		] {
+			match load("invalid", dependencies) {
+				Ok(..) => panic!("accepted {}", dependencies),
+				Err(err) => assert!(err.contains("must not contain path separators"), "{}", err),
+			}
+		}
+	}
+}
@@ -0,0 +1,111 @@
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use ureq::{Agent, AgentBuilder, Error};
+
+// Pipelines have no access to the execution, so the remote cache is process-wide.
+static REMOTE_CACHE: Mutex<Option<Arc<RemoteCache>>> = Mutex::new(None);
+
+pub fn set_remote_cache(url: Option<&str>, read_only: bool) {
+	*REMOTE_CACHE.lock().unwrap() = url.map(|url| Arc::new(RemoteCache::new(url, read_only)));
+}
+
+pub fn remote_cache() -> Option<Arc<RemoteCache>> {
+	REMOTE_CACHE.lock().unwrap().clone()
+}
+
+// An HTTP server that shares the action cache between machines. Action manifests are stored at
+// <url>/ac/<key> and blobs at <url>/cas/<hash>, and both are read with GET and written with PUT,
+// so any file server that accepts uploads will do.
+pub struct RemoteCache {
+	url: String,
+	read_only: bool,
+	agent: Agent,
+}
+
+impl RemoteCache {
+	fn new(url: &str, read_only: bool) -> RemoteCache {
+		RemoteCache {
+			url: url.trim_end_matches('/').to_owned(),
+			read_only,
+			agent: AgentBuilder::new()
+				.timeout_connect(Duration::from_secs(10))
+				.timeout(Duration::from_secs(300))
+				.build(),
+		}
+	}
+
+	// A read-only remote cache is never written to, e.g. on developer machines that should only
+	// reuse what CI has built.
+	pub fn is_read_only(&self) -> bool {
+		self.read_only
+	}
+
+	pub fn get_action(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+		self.get(&format!("{}/ac/{}", self.url, key))
+	}
+
+	// Downloads the blob with the given hash, and rejects it if its content does not match.
+	pub fn get_blob(&self, hash: &str) -> Result<Option<Vec<u8>>, String> {
+		let url = format!("{}/cas/{}", self.url, hash);
+
+		match self.get(&url)? {
+			Some(content) => {
+				let actual_hash = Sha256::digest(&content)
+					.iter()
+					.map(|byte| format!("{:02x}", byte))
+					.collect::<String>();
+
+				if actual_hash != hash {
+					return Err(format!(
+						"the checksum of '{}' does not match; expected {}, got {}",
+						url, hash, actual_hash
+					));
+				}
+
+				Ok(Some(content))
+			}
+			None => Ok(None),
+		}
+	}
+
+	pub fn put_action(&self, key: &str, content: &[u8]) -> Result<(), String> {
+		self.put(&format!("{}/ac/{}", self.url, key), content)
+	}
+
+	// Uploads the blob unless the remote cache has it already.
+	pub fn put_blob(&self, hash: &str, content: &[u8]) -> Result<(), String> {
+		let url = format!("{}/cas/{}", self.url, hash);
+
+		if self.agent.head(&url).call().is_ok() {
+			return Ok(());
+		}
+
+		self.put(&url, content)
+	}
+
+	fn get(&self, url: &str) -> Result<Option<Vec<u8>>, String> {
+		match self.agent.get(url).call() {
+			Ok(response) => {
+				let mut content = Vec::new();
+				response
+					.into_reader()
+					.read_to_end(&mut content)
+					.map_err(|err| format!("unable to download '{}': {}", url, err))?;
+
+				Ok(Some(content))
+			}
+			Err(Error::Status(404, ..)) => Ok(None),
+			Err(err) => Err(format!("unable to download '{}': {}", url, err)),
+		}
+	}
+
+	fn put(&self, url: &str, content: &[u8]) -> Result<(), String> {
+		self.agent
+			.put(url)
+			.send_bytes(content)
+			.map(|_| ())
+			.map_err(|err| format!("unable to upload '{}': {}", url, err))
+	}
+}
@@ -0,0 +1,171 @@
+use super::super::compiler::lookahead_lexer::LookaheadLexer as Lexer;
+use super::super::compiler::parser::{parse, parse_expression_input};
+use super::builtins::functions::function::build_function_map;
+use super::execution::Execution;
+use super::function::Function;
+use super::imported_pipeline::ImportedPipeline;
+use super::sub_execution::SubExecution;
+use super::value::Value;
+use std::collections::HashMap;
+use std::env::current_dir;
+use std::io::{stdin, stdout, BufRead, Write};
+use std::panic::{catch_unwind, set_hook, take_hook, AssertUnwindSafe};
+use std::sync::Arc;
+
+// An input that fails is reported and the session goes on with the next one.
+#[cfg(panic = "abort")]
+compile_error!("the REPL needs panics to unwind; build with panic = 'unwind'");
+
+const INPUT_NAME: &str = "<repl>";
+
+// Reads statements and expressions from the standard input and executes them one by one, keeping
+// variables and imported pipelines between them. Expressions are entered without a trailing
+// semicolon and print their value; everything else is executed as statements.
+pub fn repl(execution: Execution) {
+	let function_map = Arc::new(build_function_map());
+	// Imports are relative to the importing pipeline, so the input acts as a pipeline that lives in
+	// the current directory.
+	let pipeline = ImportedPipeline::new(
+		current_dir().unwrap().join(INPUT_NAME),
+		Vec::new(),
+		Vec::new(),
+	);
+	let mut sub_execution = SubExecution::new(Arc::new(execution));
+
+	// Runtime errors are panics; print only their messages instead of the whole panic report.
+	let default_hook = take_hook();
+	set_hook(Box::new(|info| {
+		let payload = info.payload();
+		let message = match payload.downcast_ref::<&str>() {
+			Some(message) => message.to_string(),
+			None => match payload.downcast_ref::<String>() {
+				Some(message) => message.clone(),
+				None => "unknown error".to_owned(),
+			},
+		};
+
+		eprintln!("Error: {}", message);
+	}));
+
+	let stdin = stdin();
+	let mut line_iter = stdin.lock().lines();
+
+	while let Some(input) = read_input(&mut line_iter) {
+		if input.trim().is_empty() {
+			continue;
+		}
+
+		catch_unwind(AssertUnwindSafe(|| {
+			execute_input(&mut sub_execution, &function_map, &pipeline, &input)
+		}))
+		.ok();
+	}
+
+	set_hook(default_hook);
+}
+
+// Reads a line, and more lines as long as brackets, braces or parentheses are left open.
+fn read_input(line_iter: &mut impl Iterator<Item = std::io::Result<String>>) -> Option<String> {
+	let mut input = String::new();
+
+	loop {
+		let prompt = if input.is_empty() {
+			"piped> "
+		} else {
+			"...    "
+		};
+		print!("{}", prompt);
+		stdout().flush().ok();
+
+		match line_iter.next() {
+			Some(Ok(line)) => {
+				input += &line;
+				input += "\n";
+			}
+			_ => {
+				println!();
+				return if input.is_empty() { None } else { Some(input) };
+			}
+		}
+
+		if open_bracket_count(&input) <= 0 {
+			return Some(input);
+		}
+	}
+}
+
+fn open_bracket_count(input: &str) -> isize {
+	let mut count = 0;
+	let mut character_iter = input.chars().peekable();
+
+	while let Some(character) = character_iter.next() {
+		match character {
+			'"' => {
+				while let Some(character) = character_iter.next() {
+					match character {
+						'\\' => {
+							character_iter.next();
+						}
+						'"' => break,
+						_ => {}
+					}
+				}
+			}
+			'/' if character_iter.peek() == Some(&'/') => {
+				for character in character_iter.by_ref() {
+					if character == '\n' {
+						break;
+					}
+				}
+			}
+			'(' | '[' | '{' => count += 1,
+			')' | ']' | '}' => count -= 1,
+			_ => {}
+		}
+	}
+
+	count
+}
+
+fn execute_input(
+	sub_execution: &mut SubExecution,
+	function_map: &Arc<HashMap<String, Box<dyn Function + Sync + Send>>>,
+	pipeline: &ImportedPipeline,
+	input: &str,
+) {
+	let mut lexer = Lexer::new(INPUT_NAME.to_owned(), input.to_owned());
+
+	if let Ok(expression_ast) = parse_expression_input(&mut lexer) {
+		let value = sub_execution.evaluate(function_map, &expression_ast);
+		print_value(sub_execution, function_map, value);
+		return;
+	}
+
+	let mut lexer = Lexer::new(INPUT_NAME.to_owned(), input.to_owned());
+
+	match parse(&mut lexer) {
+		Ok(ast_vec) => {
+			if let Some(value) = sub_execution.execute_ast_vec(function_map, pipeline, &ast_vec) {
+				print_value(sub_execution, function_map, value);
+			}
+		}
+		Err(diagnostic_vec) => {
+			for diagnostic in diagnostic_vec.iter() {
+				eprintln!("{}", diagnostic.render(lexer.src_content()));
+			}
+		}
+	}
+}
+
+fn print_value(
+	sub_execution: &mut SubExecution,
+	function_map: &Arc<HashMap<String, Box<dyn Function + Sync + Send>>>,
+	value: Value,
+) {
+	let value_type = function_map["typeof"].call(sub_execution, vec![value.clone()]);
+
+	match value {
+		Value::String(string_value) => println!("{:?} ({})", string_value, value_type),
+		value => println!("{} ({})", value, value_type),
+	}
+}
@@ -0,0 +1,145 @@
+use std::process::Child;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Mutex;
+use std::thread::sleep;
+use std::time::Duration;
+
+// How long commands get to exit on their own once the signal has been forwarded to them.
+#[cfg(unix)]
+const GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+// Pipelines have no access to the execution, so the live child processes are tracked process-wide,
+// by process ID and whether they lead a process group of their own.
+static CHILDREN: Mutex<Vec<(u32, bool)>> = Mutex::new(Vec::new());
+// The signal the process is being stopped by, or 0.
+static SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+// Tracks the child until it is released, so that signals the process receives reach it. A child
+// spawned while the process is being stopped gets the signal right away.
+pub fn track_child(pid: u32, has_process_group: bool) {
+	let mut children = CHILDREN.lock().unwrap();
+	children.push((pid, has_process_group));
+
+	let signal = SIGNAL.load(Ordering::SeqCst);
+
+	if signal != 0 {
+		send(pid, has_process_group, signal);
+	}
+}
+
+pub fn release_child(pid: u32) {
+	CHILDREN.lock().unwrap().retain(|(child, _)| *child != pid);
+}
+
+// Blocks forever once the process is being stopped by a signal, leaving the exit to the signal
+// handler, so that nothing new is started and the exit code is the one of the signal.
+pub fn park_if_interrupted() {
+	while SIGNAL.load(Ordering::SeqCst) != 0 {
+		sleep(Duration::from_secs(1));
+	}
+}
+
+// Stops the process gracefully on SIGINT and SIGTERM: the signal is forwarded to the running
+// commands, which are killed if they are still running after a grace period or another signal, and
+// then the process exits with 128 plus the signal number, as shells do.
+pub fn handle_signals() {
+	#[cfg(unix)]
+	{
+		use signal_hook::consts::{SIGINT, SIGTERM};
+		use signal_hook::iterator::Signals;
+		use std::panic::{set_hook, take_hook};
+		use std::process::exit;
+		use std::thread::spawn;
+		use std::time::Instant;
+
+		let mut signals = match Signals::new([SIGINT, SIGTERM]) {
+			Ok(signals) => signals,
+			Err(err) => {
+				eprintln!("Unable to handle signals; child processes may outlive piped.");
+				eprintln!("\tbecause: {}", err);
+				return;
+			}
+		};
+
+		// Pipelines fail as their commands get stopped, which must not end the process before
+		// the commands are gone.
+		let default_hook = take_hook();
+		set_hook(Box::new(move |info| {
+			park_if_interrupted();
+			default_hook(info);
+		}));
+
+		spawn(move || {
+			let signal = signals.forever().next().unwrap();
+			let stopped_vec = {
+				let children = CHILDREN.lock().unwrap();
+				SIGNAL.store(signal, Ordering::SeqCst);
+
+				for (pid, has_process_group) in children.iter() {
+					// Children in the foreground process group of the terminal got SIGINT from it already.
+					if signal != SIGINT || *has_process_group {
+						send(*pid, *has_process_group, signal);
+					}
+				}
+
+				children.clone()
+			};
+
+			if !stopped_vec.is_empty() {
+				eprintln!(
+					"Stopping {} running command(s); press Ctrl-C again to kill them.",
+					stopped_vec.len()
+				);
+			}
+
+			let deadline = Instant::now() + GRACE_PERIOD;
+
+			while Instant::now() < deadline
+				&& !CHILDREN.lock().unwrap().is_empty()
+				&& signals.pending().next().is_none()
+			{
+				sleep(Duration::from_millis(20));
+			}
+
+			// Processes left behind by commands that exited are killed as well, so that none outlive
+			// piped.
+			let live_vec = CHILDREN.lock().unwrap().clone();
+
+			for (pid, has_process_group) in stopped_vec
+				.iter()
+				.filter(|(_, has_process_group)| *has_process_group)
+				.chain(live_vec.iter())
+			{
+				send(*pid, *has_process_group, libc::SIGKILL);
+			}
+
+			exit(128 + signal);
+		});
+	}
+}
+
+// Kills the child, along with its process group if it leads one.
+pub fn kill(child: &mut Child, has_process_group: bool) {
+	#[cfg(unix)]
+	{
+		if has_process_group {
+			send(child.id(), true, libc::SIGKILL);
+			return;
+		}
+	}
+
+	let _ = child.kill();
+}
+
+// Sends the signal to the child, along with its process group if it leads one.
+fn send(pid: u32, has_process_group: bool, signal: i32) {
+	#[cfg(unix)]
+	{
+		let pid = pid as libc::pid_t;
+
+		// SAFETY: kill has no memory safety requirements.
+		unsafe { libc::kill(if has_process_group { -pid } else { pid }, signal) };
+	}
+	#[cfg(not(unix))]
+	let _ = (pid, has_process_group, signal);
+}
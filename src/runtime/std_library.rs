@@ -0,0 +1,28 @@
+use std::path::{Path, PathBuf};
+
+// Pipelines of the standard library live under this directory, which cannot exist on disk.
+pub const STD_ROOT: &str = "<std>";
+
+const STD_PIPELINES: [(&str, &str); 2] = [
+	("cargo.piped", include_str!("../../std/cargo.piped")),
+	("git.piped", include_str!("../../std/git.piped")),
+];
+
+// Returns the path of the standard library pipeline with the given name, e.g. "cargo".
+pub fn std_pipeline_path(name: &str) -> PathBuf {
+	let name = name.strip_suffix(".piped").unwrap_or(name);
+	Path::new(STD_ROOT).join(format!("{}.piped", name))
+}
+
+pub fn is_std_pipeline_path(path: &Path) -> bool {
+	path.starts_with(STD_ROOT)
+}
+
+pub fn std_pipeline_source(path: &Path) -> Option<&'static str> {
+	let file_name = path.strip_prefix(STD_ROOT).ok()?.to_str()?;
+
+	STD_PIPELINES
+		.iter()
+		.find(|(name, _)| *name == file_name)
+		.map(|(_, source)| *source)
+}
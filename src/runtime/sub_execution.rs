@@ -1,17 +1,24 @@
-use super::super::compiler::parser::{ExpressionAST, LiteralAST, AST};
+use super::super::compiler::lexer::Token;
+use super::super::compiler::parser::{ExpressionAST, LiteralAST, ModifierAST, PipelineAST, AST};
 use super::builtins::pipelines::pipeline::build_pipeline_map;
 use super::builtins::variables::variable::build_variable_map;
+use super::cancellation::{current_cancellation, enter_cancellation, CancellationToken};
+use super::dry_run::{wrap_builtin_pipeline, wrap_imported_pipeline};
 use super::execution::Execution;
 use super::function::Function;
-use super::imported_pipeline::ImportedPipeline;
-use super::pipeline::{PipelineExecutionResult, PipelineFactory};
-use super::value::Value;
-use path_absolutize::Absolutize;
-use regex::Regex;
+use super::imported_pipeline::{resolve_import_path, ImportedPipeline};
+use super::modifier::with_modifiers;
+use super::output::enter_job;
+use super::pipeline::{
+	validate_argument_map, PipelineExecution, PipelineExecutionResult, PipelineFactory,
+};
+use super::value::{Value, ValueType};
 use std::collections::HashMap;
-use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::thread::{spawn, JoinHandle};
+use std::time::Duration;
 
 enum SubExecutionResult {
 	Done,
@@ -20,18 +27,66 @@ enum SubExecutionResult {
 	Return(Option<Value>),
 }
 
+// A pipeline running in the background, started by a @nonblock statement.
+struct Job {
+	// How the job is referred to when it failed or was cancelled.
+	label: String,
+	result_as: Option<String>,
+	handle: JoinHandle<JobOutcome>,
+}
+
+enum JobOutcome {
+	Finished(PipelineExecutionResult),
+	// The pipeline failed or raised an error, and the other jobs were cancelled because of it.
+	Failed,
+	// The job was cancelled because another one failed.
+	Cancelled,
+}
+
+// An @import statement through which an imported pipeline is being executed.
+#[derive(Clone)]
+struct ImportStep {
+	importer_path: PathBuf,
+	line_number: usize,
+	imported_path: PathBuf,
+}
+
 pub struct SubExecution {
 	execution: Arc<Execution>,
 	variable_map: HashMap<String, Value>,
-	pipeline_factory_map: HashMap<String, Box<PipelineFactory>>,
+	pipeline_factory_map: HashMap<String, Arc<PipelineFactory>>,
+	// The imports that led to this execution, from the entry pipeline on.
+	import_chain: Vec<ImportStep>,
+	// Imported pipelines by name, for accessing their exports.
+	module_map: HashMap<String, (Arc<ImportedPipeline>, Vec<ImportStep>)>,
+	export_map: HashMap<String, Value>,
+	// The parent of the cancellation tokens of nonblock pipelines; a failing one cancels the others.
+	job_cancellation: CancellationToken,
 }
 
 impl SubExecution {
 	pub fn new(execution: Arc<Execution>) -> SubExecution {
+		let pipeline_factory_map = build_pipeline_map()
+			.into_iter()
+			.map(|(name, factory)| {
+				let factory = if execution.dry_run() {
+					wrap_builtin_pipeline(name.clone(), factory)
+				} else {
+					factory
+				};
+
+				(name, Arc::from(factory))
+			})
+			.collect();
+
 		SubExecution {
 			execution,
 			variable_map: build_variable_map(),
-			pipeline_factory_map: build_pipeline_map(),
+			pipeline_factory_map,
+			import_chain: Vec::new(),
+			module_map: HashMap::new(),
+			export_map: HashMap::new(),
+			job_cancellation: current_cancellation().child(),
 		}
 	}
 
@@ -43,23 +98,39 @@ impl SubExecution {
 		self.variable_map.insert(name, value);
 	}
 
+	pub fn watch_glob(&self, pattern: String) {
+		self.execution.watch_glob(pattern);
+	}
+
 	pub fn execute(
 		&mut self,
 		function_map: &Arc<HashMap<String, Box<dyn Function + Sync + Send>>>,
 		pipeline: &ImportedPipeline,
 	) -> Option<Value> {
-		let (result, named_pipelines, unnamed_pipelines) =
-			self.__execute(&function_map, pipeline, pipeline.ast_vec(), false);
+		self.execute_ast_vec(function_map, pipeline, pipeline.ast_vec())
+	}
 
-		for (_, pipeline) in named_pipelines.into_iter() {
-			for pipeline in pipeline {
-				pipeline.1.join().unwrap();
-			}
-		}
+	// Executes the given statements as if they were written in the given pipeline, and waits for
+	// all nonblock pipelines they started.
+	pub fn execute_ast_vec(
+		&mut self,
+		function_map: &Arc<HashMap<String, Box<dyn Function + Sync + Send>>>,
+		pipeline: &ImportedPipeline,
+		ast_vec: &Vec<AST>,
+	) -> Option<Value> {
+		// Jobs cancelled by an earlier run, e.g. of the REPL, must not affect this one.
+		self.job_cancellation = current_cancellation().child();
 
-		for pipeline in unnamed_pipelines {
-			pipeline.1.join().unwrap();
-		}
+		let (result, named_pipelines, unnamed_pipelines) =
+			self.__execute(&function_map, pipeline, ast_vec, false);
+
+		self.await_jobs(
+			named_pipelines
+				.into_values()
+				.flatten()
+				.chain(unnamed_pipelines)
+				.collect(),
+		);
 
 		match result {
 			SubExecutionResult::Done => None,
@@ -74,17 +145,9 @@ impl SubExecution {
 		pipeline: &ImportedPipeline,
 		ast_vec: &Vec<AST>,
 		allow_break_and_continue: bool,
-	) -> (
-		SubExecutionResult,
-		HashMap<String, Vec<(Option<String>, JoinHandle<PipelineExecutionResult>)>>,
-		Vec<(Option<String>, JoinHandle<PipelineExecutionResult>)>,
-	) {
-		let mut named_pipeline_map: HashMap<
-			String,
-			Vec<(Option<String>, JoinHandle<PipelineExecutionResult>)>,
-		> = HashMap::new();
-		let mut unnamed_pipeline_vec: Vec<(Option<String>, JoinHandle<PipelineExecutionResult>)> =
-			Vec::new();
+	) -> (SubExecutionResult, HashMap<String, Vec<Job>>, Vec<Job>) {
+		let mut named_pipeline_map: HashMap<String, Vec<Job>> = HashMap::new();
+		let mut unnamed_pipeline_vec: Vec<Job> = Vec::new();
 
 		for ast in ast_vec.iter() {
 			match ast {
@@ -92,64 +155,131 @@ impl SubExecution {
 					if let Value::String(path) =
 						self.expression_to_value(function_map, &import_ast.path)
 					{
-						let mut base_path = pipeline.path().clone();
-						base_path.pop();
-
-						let path = PathBuf::from(
-							base_path
-								.join(Path::new(
-									&*Regex::new("[/\\\\]")
-										.unwrap()
-										.replace_all(&path, &*MAIN_SEPARATOR.to_string()),
-								))
-								.absolutize()
-								.unwrap(),
-						);
-
-						match self.execution.import(path) {
+						let path = match resolve_import_path(pipeline.path(), &path) {
+							Ok(path) => path,
+							Err(err) => panic!("unable to resolve the import '{}': {}", path, err),
+						};
+						let mut import_chain = self.import_chain.clone();
+						import_chain.push(ImportStep {
+							importer_path: pipeline.path().clone(),
+							line_number: import_ast.name.line_number,
+							imported_path: path.clone(),
+						});
+
+						match self.execution.import(path.clone()) {
 							Ok(imported_pipeline) => {
+								self.module_map.insert(
+									import_ast.name.token_content.clone(),
+									(imported_pipeline.clone(), import_chain.clone()),
+								);
+
 								let function_map = function_map.clone();
 								let execution = self.execution.clone();
+								let pipeline_name = import_ast.name.token_content.clone();
+								let factory: Box<PipelineFactory> = Box::new(move |argument_map| {
+									check_import_cycle(&import_chain, imported_pipeline.path());
+
+									if !imported_pipeline.parameter_vec().is_empty() {
+										if let Err(err) = validate_argument_map(
+											imported_pipeline.parameter_vec(),
+											argument_map,
+										) {
+											panic!(
+												"invalid arguments for pipeline '{}': {}",
+												pipeline_name, err
+											);
+										}
+									}
+
+									let function_map = function_map.clone();
+									let variable_map = argument_map.clone();
+									let imported_pipeline = imported_pipeline.clone();
+									let execution = execution.clone();
+									let import_chain = import_chain.clone();
+
+									Box::new(move || {
+										let mut sub_execution =
+											SubExecution::new(execution.clone());
+										sub_execution.import_chain = import_chain.clone();
+
+										for (name, value) in variable_map.iter() {
+											sub_execution.set_variable(name.clone(), value.clone());
+										}
+
+										PipelineExecutionResult {
+											success: true,
+											result: sub_execution
+												.execute(&function_map, &imported_pipeline),
+										}
+									})
+								});
+								let factory = if self.execution.dry_run() {
+									wrap_imported_pipeline(
+										import_ast.name.token_content.clone(),
+										factory,
+									)
+								} else {
+									factory
+								};
 
 								self.pipeline_factory_map.insert(
 									import_ast.name.token_content.clone(),
-									Box::new(move |argument_map| {
-										let function_map = function_map.clone();
-										let variable_map = argument_map.clone();
-										let imported_pipeline = imported_pipeline.clone();
-										let execution = execution.clone();
-
-										Box::new(move || {
-											let mut sub_execution =
-												SubExecution::new(execution.clone());
-
-											for (name, value) in variable_map.iter() {
-												sub_execution
-													.set_variable(name.clone(), value.clone());
-											}
-
-											PipelineExecutionResult {
-												success: true,
-												result: sub_execution
-													.execute(&function_map, &imported_pipeline),
-											}
-										})
-									}),
+									Arc::from(factory),
 								);
 							}
 							Err(err) => {
-								panic!("unable to import pipeline: {}", err);
+								panic!(
+									"unable to import '{}': {}{}",
+									path.display(),
+									err,
+									describe_import_chain(&import_chain)
+								);
 							}
 						}
 					} else {
 						panic!("path must be a string type");
 					}
 				}
+				AST::Param(param_ast) => {
+					if !self
+						.variable_map
+						.contains_key(&param_ast.name.token_content)
+					{
+						match &param_ast.default {
+							Some(default) => {
+								let value = self.expression_to_value(function_map, default);
+
+								if ValueType::from_name(&param_ast.value_type.token_content)
+									!= Some(value.value_type())
+								{
+									panic!(
+										"default value of parameter '{}' must be a '{}' type",
+										&param_ast.name.token_content,
+										&param_ast.value_type.token_content
+									);
+								}
+
+								self.variable_map
+									.insert(param_ast.name.token_content.clone(), value);
+							}
+							None => {
+								panic!("parameter '{}' is required", &param_ast.name.token_content)
+							}
+						}
+					}
+				}
 				AST::Set(set_ast) => {
 					let value = self.expression_to_value(function_map, &set_ast.value);
 					self.variable_map
 						.insert(set_ast.name.token_content.clone(), value);
 				}
+				AST::Export(export_ast) => {
+					let value = self.expression_to_value(function_map, &export_ast.value);
+					self.variable_map
+						.insert(export_ast.name.token_content.clone(), value.clone());
+					self.export_map
+						.insert(export_ast.name.token_content.clone(), value);
+				}
 				AST::Print(print_ast) => {
 					for expression_ast in print_ast.expression_vec.iter() {
 						print!("{}", self.expression_to_value(function_map, expression_ast));
@@ -175,72 +305,27 @@ impl SubExecution {
 					);
 				}
 				AST::Await(await_ast) => match &await_ast.name {
-					Some(name) => match named_pipeline_map.remove(&name.token_content) {
-						Some(named_pipeline_vec) => {
-							for named_pipeline in named_pipeline_vec {
-								let result = named_pipeline.1.join().unwrap();
-
-								match named_pipeline.0 {
-									Some(result_as) => match result.result {
-										Some(result) => {
-											self.variable_map.insert(result_as, result);
-										}
-										None => (),
-									},
-									None => (),
-								}
-							}
+					Some(name) => {
+						if let Some(named_pipeline_vec) =
+							named_pipeline_map.remove(&name.token_content)
+						{
+							self.await_jobs(named_pipeline_vec);
 						}
-						None => {}
-					},
+					}
 					None => {
-						for unnamed_pipeline in unnamed_pipeline_vec {
-							let result = unnamed_pipeline.1.join().unwrap();
-
-							match unnamed_pipeline.0 {
-								Some(result_as) => match result.result {
-									Some(result) => {
-										self.variable_map.insert(result_as, result);
-									}
-									None => (),
-								},
-								None => (),
-							}
-						}
+						self.await_jobs(unnamed_pipeline_vec);
 						unnamed_pipeline_vec = Vec::new();
 					}
 				},
 				AST::AwaitAll => {
-					for named_pipeline_vec in named_pipeline_map.into_iter() {
-						for named_pipeline in named_pipeline_vec.1 {
-							let result = named_pipeline.1.join().unwrap();
-
-							match named_pipeline.0 {
-								Some(result_as) => match result.result {
-									Some(result) => {
-										self.variable_map.insert(result_as, result);
-									}
-									None => (),
-								},
-								None => (),
-							}
-						}
-					}
+					self.await_jobs(
+						named_pipeline_map
+							.into_values()
+							.flatten()
+							.chain(unnamed_pipeline_vec)
+							.collect(),
+					);
 					named_pipeline_map = HashMap::new();
-
-					for unnamed_pipeline in unnamed_pipeline_vec {
-						let result = unnamed_pipeline.1.join().unwrap();
-
-						match unnamed_pipeline.0 {
-							Some(result_as) => match result.result {
-								Some(result) => {
-									self.variable_map.insert(result_as, result);
-								}
-								None => (),
-							},
-							None => (),
-						}
-					}
 					unnamed_pipeline_vec = Vec::new();
 				}
 				AST::NonBlock(non_block_ast) => {
@@ -256,55 +341,56 @@ impl SubExecution {
 						})
 						.collect();
 
-					let mut pipeline = match self
-						.pipeline_factory_map
-						.get(&non_block_ast.pipeline.name.token_content)
-					{
-						Some(pipeline) => pipeline(&argument_map),
-						None => panic!(
-							"undefined pipeline '{}' used",
-							&non_block_ast.pipeline.name.token_content
-						),
-					};
-
-					let pipeline_join_handle = spawn(move || pipeline());
+					let mut pipeline =
+						self.create_pipeline(function_map, &non_block_ast.pipeline, &argument_map);
+
+					let execution = self.execution.clone();
+					let token = non_block_ast.pipeline.name.clone();
+					let job_name = non_block_ast
+						.name
+						.as_ref()
+						.map(|name| name.token_content.clone());
+					let job_cancellation = self.job_cancellation.clone();
+					let cancellation = job_cancellation.child();
+					let handle = spawn(move || {
+						enter_job(job_name);
+						enter_cancellation(cancellation.clone());
+						execution.log().enter_track(&token);
+
+						let result = catch_unwind(AssertUnwindSafe(|| {
+							execution
+								.log()
+								.record(&token, &argument_map, &mut *pipeline)
+						}));
 
-					match &non_block_ast.name {
-						Some(name) => match named_pipeline_map.get_mut(&name.token_content) {
-							Some(named_pipeline_vec) => {
-								named_pipeline_vec.push((
-									non_block_ast
-										.pipeline
-										.result_as
-										.as_ref()
-										.map(|result_as| result_as.token_content.clone()),
-									pipeline_join_handle,
-								));
-							}
-							None => {
-								named_pipeline_map.insert(
-									name.token_content.clone(),
-									vec![(
-										non_block_ast
-											.pipeline
-											.result_as
-											.as_ref()
-											.map(|result_as| result_as.token_content.clone()),
-										pipeline_join_handle,
-									)],
-								);
+						match result {
+							Ok(result) if result.success => JobOutcome::Finished(result),
+							_ if cancellation.is_cancelled() => JobOutcome::Cancelled,
+							_ => {
+								job_cancellation.cancel();
+								JobOutcome::Failed
 							}
-						},
-						None => {
-							unnamed_pipeline_vec.push((
-								non_block_ast
-									.pipeline
-									.result_as
-									.as_ref()
-									.map(|result_as| result_as.token_content.clone()),
-								pipeline_join_handle,
-							));
 						}
+					});
+					let job = Job {
+						label: match &non_block_ast.name {
+							Some(name) => format!("'{}'", name.token_content),
+							None => describe_invocation(&non_block_ast.pipeline.name),
+						},
+						result_as: non_block_ast
+							.pipeline
+							.result_as
+							.as_ref()
+							.map(|result_as| result_as.token_content.clone()),
+						handle,
+					};
+
+					match &non_block_ast.name {
+						Some(name) => named_pipeline_map
+							.entry(name.token_content.clone())
+							.or_default()
+							.push(job),
+						None => unnamed_pipeline_vec.push(job),
 					}
 				}
 				AST::For(for_ast) => {
@@ -351,7 +437,7 @@ impl SubExecution {
 						panic!("iterable must be a array type");
 					}
 				}
-				AST::Break => {
+				AST::Break(..) => {
 					if !allow_break_and_continue {
 						panic!("break statement is not allowed here");
 					}
@@ -362,7 +448,7 @@ impl SubExecution {
 						unnamed_pipeline_vec,
 					);
 				}
-				AST::Continue => {
+				AST::Continue(..) => {
 					if !allow_break_and_continue {
 						panic!("continue statement is not allowed here");
 					}
@@ -472,6 +558,33 @@ impl SubExecution {
 						}
 					}
 				}
+				AST::Assert(assert_ast) => {
+					if !match self.expression_to_value(function_map, &assert_ast.criteria) {
+						Value::Array(array_value) => !array_value.is_empty(),
+						Value::Dictionary(dictionary_value) => !dictionary_value.is_empty(),
+						Value::Bool(bool_value) => bool_value,
+						Value::Integer(integer_value) => integer_value != 0,
+						Value::String(string_value) => !string_value.is_empty(),
+					} {
+						let location = format!(
+							"{}:{}:{}",
+							assert_ast.token.file_path,
+							assert_ast.token.line_number,
+							assert_ast.token.line_offset
+						);
+
+						match &assert_ast.message {
+							Some(message) => panic!(
+								"assertion failed at {}: {}",
+								location,
+								self.expression_to_value(function_map, message)
+							),
+							None => panic!("assertion failed at {}", location),
+						}
+					}
+				}
+				// Tests only run through the test subcommand; see test_runner.
+				AST::Test(..) => {}
 				AST::Pipeline(pipeline_ast) => {
 					let argument_map = pipeline_ast
 						.argument_vec
@@ -484,28 +597,19 @@ impl SubExecution {
 						})
 						.collect();
 
-					match self
-						.pipeline_factory_map
-						.get(&pipeline_ast.name.token_content)
+					let mut pipeline =
+						self.create_pipeline(function_map, pipeline_ast, &argument_map);
+					let result = self.execution.log().record(
+						&pipeline_ast.name,
+						&argument_map,
+						&mut *pipeline,
+					);
+
+					if let (Some(result_as), Some(result)) =
+						(&pipeline_ast.result_as, result.result)
 					{
-						Some(pipeline) => {
-							let result = pipeline(&argument_map)();
-
-							match result.result {
-								Some(result) => match &pipeline_ast.result_as {
-									Some(result_as) => {
-										self.variable_map
-											.insert(result_as.token_content.clone(), result);
-									}
-									None => (),
-								},
-								None => (),
-							}
-						}
-						None => panic!(
-							"undefined pipeline '{}' used",
-							&pipeline_ast.name.token_content
-						),
+						self.variable_map
+							.insert(result_as.token_content.clone(), result);
 					}
 				}
 				AST::Call(call_ast) => {
@@ -534,6 +638,125 @@ impl SubExecution {
 		)
 	}
 
+	// Creates the execution of a pipeline invocation, wrapped by its @retry and @timeout modifiers
+	// if it has any. Every attempt gets an execution of its own, as one can be run only once.
+	fn create_pipeline(
+		&mut self,
+		function_map: &Arc<HashMap<String, Box<dyn Function + Sync + Send>>>,
+		pipeline_ast: &PipelineAST,
+		argument_map: &HashMap<String, Value>,
+	) -> Box<PipelineExecution> {
+		let retry = pipeline_ast.retry.as_ref().map(|retry| {
+			match self.evaluate_modifier(function_map, retry) {
+				retry if retry >= 0 => retry as usize,
+				retry => panic!("'@retry' must not be negative, got {}", retry),
+			}
+		});
+		let timeout = pipeline_ast.timeout.as_ref().map(|timeout| {
+			match self.evaluate_modifier(function_map, timeout) {
+				timeout if timeout > 0 => Duration::from_secs(timeout as u64),
+				timeout => panic!("'@timeout' must be positive, got {}", timeout),
+			}
+		});
+
+		let factory = match self
+			.pipeline_factory_map
+			.get(&pipeline_ast.name.token_content)
+		{
+			Some(factory) => factory.clone(),
+			None => panic!(
+				"undefined pipeline '{}' used",
+				&pipeline_ast.name.token_content
+			),
+		};
+
+		if retry.is_none() && timeout.is_none() {
+			return factory(argument_map);
+		}
+
+		// The first attempt is created right away, so that invalid arguments fail the invocation
+		// instead of its first attempt; retries are created only once they are needed.
+		let mut first_attempt = Some(factory(argument_map));
+		let argument_map = argument_map.clone();
+
+		with_modifiers(
+			self.execution.clone(),
+			describe_invocation(&pipeline_ast.name),
+			retry.unwrap_or(0) + 1,
+			move || {
+				first_attempt
+					.take()
+					.unwrap_or_else(|| factory(&argument_map))
+			},
+			timeout,
+		)
+	}
+
+	// Waits for the jobs and binds their results. Unless every job finished, the failed and
+	// cancelled ones are reported and the execution stops, as carrying on without their work
+	// would fail anyway.
+	fn await_jobs(&mut self, job_vec: Vec<Job>) {
+		let mut failed_vec = Vec::new();
+		let mut cancelled_vec = Vec::new();
+
+		for job in job_vec {
+			match job.handle.join().unwrap() {
+				JobOutcome::Finished(result) => {
+					if let (Some(result_as), Some(result)) = (job.result_as, result.result) {
+						self.variable_map.insert(result_as, result);
+					}
+				}
+				JobOutcome::Failed => failed_vec.push(job.label),
+				JobOutcome::Cancelled => cancelled_vec.push(job.label),
+			}
+		}
+
+		if failed_vec.is_empty() && cancelled_vec.is_empty() {
+			return;
+		}
+
+		// Jobs started after the failure is caught, e.g. by the REPL, must not be cancelled by it.
+		self.job_cancellation = current_cancellation().child();
+
+		for label in failed_vec.iter() {
+			eprintln!("Nonblock pipeline {} failed.", label);
+		}
+
+		for label in cancelled_vec.iter() {
+			eprintln!("Nonblock pipeline {} was cancelled.", label);
+		}
+
+		panic!(
+			"nonblock pipelines did not finish: {} failed, {} cancelled",
+			failed_vec.len(),
+			cancelled_vec.len()
+		);
+	}
+
+	fn evaluate_modifier(
+		&mut self,
+		function_map: &Arc<HashMap<String, Box<dyn Function + Sync + Send>>>,
+		modifier_ast: &ModifierAST,
+	) -> i64 {
+		match self.expression_to_value(function_map, &modifier_ast.value) {
+			Value::Integer(value) => value,
+			value => panic!(
+				"'@{}' must be a '{:#?}' type, got '{:#?}'",
+				modifier_ast.keyword.token_content,
+				ValueType::Integer,
+				value.value_type()
+			),
+		}
+	}
+
+	pub fn evaluate(
+		&mut self,
+		function_map: &Arc<HashMap<String, Box<dyn Function + Sync + Send>>>,
+		expression_ast: &ExpressionAST,
+	) -> Value {
+		self.expression_to_value(function_map, expression_ast)
+	}
+
 	fn expression_to_value(
 		&mut self,
 		function_map: &Arc<HashMap<String, Box<dyn Function + Sync + Send>>>,
@@ -562,6 +785,37 @@ impl SubExecution {
 				Some(value) => value.clone(),
 				None => panic!("undefined variable '{}' used", &token.token_content),
 			},
+			ExpressionAST::Member(member_ast) => {
+				let (module, import_chain) =
+					match self.module_map.get(&member_ast.module.token_content) {
+						Some(module) => module.clone(),
+						None => panic!(
+							"undefined pipeline '{}' used",
+							&member_ast.module.token_content
+						),
+					};
+				let execution = self.execution.clone();
+
+				// Checked before waiting for the exports, which would never be ready otherwise.
+				check_import_cycle(&import_chain, module.path());
+
+				match self.execution.module_export(
+					module.path(),
+					&member_ast.name.token_content,
+					|| {
+						let mut sub_execution = SubExecution::new(execution);
+						sub_execution.import_chain = import_chain.clone();
+						sub_execution.execute(function_map, &module);
+						sub_execution.export_map
+					},
+				) {
+					Some(value) => value,
+					None => panic!(
+						"pipeline '{}' does not export '{}'",
+						&member_ast.module.token_content, &member_ast.name.token_content
+					),
+				}
+			}
 			ExpressionAST::Call(call_ast) => {
 				let argument_expression_vec = call_ast
 					.argument_vec
@@ -585,3 +839,164 @@ fn literal_to_value(literal_ast: &LiteralAST) -> Value {
 		LiteralAST::String(token) => Value::String(token.token_content.clone()),
 	}
 }
+
+// Describes a pipeline invocation by its name and location, for messages about it.
+fn describe_invocation(name: &Token) -> String {
+	format!(
+		"'{}' at {}:{}:{}",
+		name.token_content, name.file_path, name.line_number, name.line_offset
+	)
+}
+
+// Imports are cached, so a pipeline that uses one of the pipelines it is executed through would
+// recurse forever.
+fn check_import_cycle(import_chain: &[ImportStep], imported_path: &Path) {
+	if import_chain
+		.iter()
+		.any(|step| step.importer_path == imported_path)
+	{
+		panic!(
+			"circular import of '{}'{}",
+			imported_path.display(),
+			describe_import_chain(import_chain)
+		);
+	}
+}
+
+fn describe_import_chain(import_chain: &[ImportStep]) -> String {
+	import_chain
+		.iter()
+		.map(|step| {
+			format!(
+				"\n\t{}:{} imports {}",
+				step.importer_path.display(),
+				step.line_number,
+				step.imported_path.display()
+			)
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::any::Any;
+	use std::fs::{remove_file, write};
+	use std::process;
+
+	fn job(label: &str, outcome: impl FnOnce() -> JobOutcome + Send + 'static) -> Job {
+		Job {
+			label: format!("'{}'", label),
+			result_as: Some(label.to_owned()),
+			handle: spawn(outcome),
+		}
+	}
+
+	fn finished(value: i64) -> JobOutcome {
+		JobOutcome::Finished(PipelineExecutionResult {
+			success: true,
+			result: Some(Value::Integer(value)),
+		})
+	}
+
+	fn panic_message(payload: Box<dyn Any + Send>) -> String {
+		payload
+			.downcast::<String>()
+			.map(|message| *message)
+			.unwrap_or_default()
+	}
+
+	#[test]
+	fn binds_the_results_of_finished_jobs() {
+		let mut sub_execution = SubExecution::new(Arc::new(Execution::new()));
+		sub_execution.await_jobs(vec![job("a", || finished(1)), job("b", || finished(2))]);
+
+		match sub_execution.get_variable("b") {
+			Some(Value::Integer(value)) => assert_eq!(*value, 2),
+			_ => panic!("expected the result of 'b'"),
+		}
+	}
+
+	#[test]
+	fn fails_on_a_failed_job_even_if_nothing_was_cancelled() {
+		let mut sub_execution = SubExecution::new(Arc::new(Execution::new()));
+		let result = catch_unwind(AssertUnwindSafe(|| {
+			sub_execution.await_jobs(vec![
+				job("a", || finished(1)),
+				job("b", || JobOutcome::Failed),
+			])
+		}));
+
+		assert_eq!(
+			panic_message(result.unwrap_err()),
+			"nonblock pipelines did not finish: 1 failed, 0 cancelled"
+		);
+	}
+
+	#[test]
+	fn reports_cancelled_jobs() {
+		let mut sub_execution = SubExecution::new(Arc::new(Execution::new()));
+		let result = catch_unwind(AssertUnwindSafe(|| {
+			sub_execution.await_jobs(vec![
+				job("a", || JobOutcome::Failed),
+				job("b", || JobOutcome::Cancelled),
+			])
+		}));
+
+		assert_eq!(
+			panic_message(result.unwrap_err()),
+			"nonblock pipelines did not finish: 1 failed, 1 cancelled"
+		);
+		assert!(!sub_execution.job_cancellation.is_cancelled());
+	}
+
+	// Runs the source as a pipeline file and returns whether the run succeeded.
+	fn run(name: &str, source: &str) -> bool {
+		let path = std::env::temp_dir().join(format!("piped-{}-{}.piped", name, process::id()));
+		write(&path, source).unwrap();
+
+		let pipeline = ImportedPipeline::import(&path);
+		remove_file(&path).unwrap();
+		let pipeline = pipeline.ok().unwrap();
+		let execution = Arc::new(Execution::new());
+
+		catch_unwind(AssertUnwindSafe(|| execution.run(pipeline))).is_ok()
+	}
+
+	#[test]
+	fn fails_the_run_on_a_nonblock_pipeline_that_failed() {
+		assert!(!run(
+			"nonblock",
+			"@nonblock exec cmd=\"sh\" params=[\"-c\", \"exit 1\"];\n@await;\n",
+		));
+	}
+
+	#[test]
+	fn fails_the_run_once_every_attempt_failed() {
+		assert!(!run("retry-blocking", "@retry 1 exec cmd=\"false\";\n",));
+		assert!(!run(
+			"retry-nonblock",
+			"@nonblock @retry 1 exec cmd=\"false\";\n@await;\n",
+		));
+	}
+
+	#[test]
+	fn succeeds_once_a_retried_attempt_succeeded() {
+		let marker = std::env::temp_dir().join(format!("piped-retry-marker-{}", process::id()));
+		// The command fails the first time it runs, and succeeds the second.
+		let command = format!(
+			"test -e '{0}' || {{ touch '{0}'; exit 1; }}",
+			marker.display()
+		);
+		let succeeded = run(
+			"retry-flaky",
+			&format!(
+				"@retry 1 exec cmd=\"sh\" params=[\"-c\", \"{}\"];\n",
+				command
+			),
+		);
+		remove_file(&marker).unwrap();
+
+		assert!(succeeded);
+	}
+}
@@ -0,0 +1,339 @@
+use super::super::compiler::parser::AST;
+use super::builtins::functions::function::build_function_map;
+use super::execution::Execution;
+use super::imported_pipeline::ImportedPipeline;
+use super::sub_execution::SubExecution;
+use std::fs::{read_dir, write};
+use std::panic::{catch_unwind, set_hook, take_hook, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// A failed assertion or runtime error fails its test only, so test panics are caught.
+#[cfg(panic = "abort")]
+compile_error!("the test runner needs panics to unwind; build with panic = 'unwind'");
+
+const TEST_FILE_SUFFIX: &str = ".test.piped";
+
+struct TestResult {
+	name: String,
+	file_path: String,
+	line_number: Option<usize>,
+	duration: Duration,
+	failure: Option<String>,
+}
+
+impl TestResult {
+	fn label(&self) -> String {
+		match self.line_number {
+			Some(line_number) => format!("\"{}\" ({}:{})", self.name, self.file_path, line_number),
+			None => self.name.clone(),
+		}
+	}
+}
+
+// Runs every test found in the given files and directories and returns whether all of them
+// passed. Each @test block is a test of its own; files named *.test.piped without any @test
+// block, and files given explicitly, are executed as a single test.
+pub fn run_tests(input_vec: &[PathBuf], junit_path: Option<&Path>) -> bool {
+	let mut file_vec = Vec::new();
+
+	for input in input_vec.iter() {
+		if input.is_dir() {
+			collect_files(input, &mut file_vec);
+		} else {
+			file_vec.push((input.clone(), true));
+		}
+	}
+
+	// Runtime errors are panics; keep their messages as the failure reasons instead of printing
+	// the whole panic report. Nonblock pipelines panic on threads of their own, so the first
+	// message is the one that explains the failure.
+	let message_vec = Arc::new(Mutex::new(Vec::new()));
+	let default_hook = take_hook();
+	{
+		let message_vec = message_vec.clone();
+		set_hook(Box::new(move |info| {
+			let payload = info.payload();
+			let message = match payload.downcast_ref::<&str>() {
+				Some(message) => message.to_string(),
+				None => match payload.downcast_ref::<String>() {
+					Some(message) => message.clone(),
+					None => "unknown error".to_owned(),
+				},
+			};
+
+			message_vec.lock().unwrap().push(message);
+		}));
+	}
+
+	let started_at = Instant::now();
+	let mut result_vec = Vec::new();
+
+	for (path, explicit) in file_vec.iter() {
+		let display_path = path.display().to_string();
+		let is_test_file = display_path.ends_with(TEST_FILE_SUFFIX);
+		let pipeline = match ImportedPipeline::import(path) {
+			Ok(pipeline) => pipeline,
+			Err(err) => {
+				if *explicit || is_test_file {
+					result_vec.push(TestResult {
+						name: display_path.clone(),
+						file_path: display_path,
+						line_number: None,
+						duration: Duration::default(),
+						failure: Some(err.to_string()),
+					});
+					report(result_vec.last().unwrap());
+				}
+				continue;
+			}
+		};
+		let test_ast_vec = pipeline
+			.ast_vec()
+			.iter()
+			.filter_map(|ast| match ast {
+				AST::Test(test_ast) => Some(test_ast),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+
+		if test_ast_vec.is_empty() {
+			if *explicit || is_test_file {
+				result_vec.push(run_test(
+					&pipeline,
+					None,
+					display_path.clone(),
+					&display_path,
+					None,
+					&message_vec,
+				));
+				report(result_vec.last().unwrap());
+			}
+			continue;
+		}
+
+		for test_ast in test_ast_vec {
+			result_vec.push(run_test(
+				&pipeline,
+				Some(&test_ast.body_ast_vec),
+				test_ast.name.token_content.clone(),
+				&display_path,
+				Some(test_ast.name.line_number),
+				&message_vec,
+			));
+			report(result_vec.last().unwrap());
+		}
+	}
+
+	set_hook(default_hook);
+
+	let failed_vec = result_vec
+		.iter()
+		.filter(|result| result.failure.is_some())
+		.collect::<Vec<_>>();
+
+	if !failed_vec.is_empty() {
+		println!();
+		println!("failures:");
+
+		for result in failed_vec.iter() {
+			println!();
+			println!("---- {} ----", result.label());
+			println!("{}", result.failure.as_ref().unwrap());
+		}
+	}
+
+	println!();
+	println!(
+		"test result: {}. {} passed; {} failed; finished in {:.3}s.",
+		if failed_vec.is_empty() {
+			"ok"
+		} else {
+			"FAILED"
+		},
+		result_vec.len() - failed_vec.len(),
+		failed_vec.len(),
+		started_at.elapsed().as_secs_f64()
+	);
+
+	if let Some(junit_path) = junit_path {
+		if let Err(err) = write(junit_path, junit_xml(&result_vec)) {
+			eprintln!("Unable to write the JUnit report: {}", junit_path.display());
+			eprintln!("\tbecause: {}", err);
+			return false;
+		}
+	}
+
+	failed_vec.is_empty()
+}
+
+// Collects the pipeline files in the directory and its subdirectories in a stable order. Hidden
+// directories are skipped.
+fn collect_files(directory: &Path, file_vec: &mut Vec<(PathBuf, bool)>) {
+	let mut path_vec = match read_dir(directory) {
+		Ok(entries) => entries
+			.flatten()
+			.map(|entry| entry.path())
+			.collect::<Vec<_>>(),
+		Err(..) => return,
+	};
+	path_vec.sort();
+
+	for path in path_vec {
+		let file_name = path
+			.file_name()
+			.map(|file_name| file_name.to_string_lossy().into_owned())
+			.unwrap_or_default();
+
+		if path.is_dir() {
+			if !file_name.starts_with('.') {
+				collect_files(&path, file_vec);
+			}
+		} else if file_name.ends_with(".piped") {
+			file_vec.push((path, false));
+		}
+	}
+}
+
+// Executes the pipeline in an execution of its own, followed by the test body if any, so that no
+// variables or imports leak from one test into another.
+fn run_test(
+	pipeline: &ImportedPipeline,
+	body_ast_vec: Option<&Vec<AST>>,
+	name: String,
+	file_path: &str,
+	line_number: Option<usize>,
+	message_vec: &Mutex<Vec<String>>,
+) -> TestResult {
+	message_vec.lock().unwrap().clear();
+
+	let started_at = Instant::now();
+	let passed = catch_unwind(AssertUnwindSafe(|| {
+		let function_map = Arc::new(build_function_map());
+		let mut sub_execution = SubExecution::new(Arc::new(Execution::new()));
+
+		sub_execution.execute(&function_map, pipeline);
+
+		if let Some(body_ast_vec) = body_ast_vec {
+			sub_execution.execute_ast_vec(&function_map, pipeline, body_ast_vec);
+		}
+	}))
+	.is_ok();
+
+	TestResult {
+		name,
+		file_path: file_path.to_owned(),
+		line_number,
+		duration: started_at.elapsed(),
+		failure: if passed {
+			None
+		} else {
+			Some(
+				message_vec
+					.lock()
+					.unwrap()
+					.first()
+					.cloned()
+					.unwrap_or_else(|| "unknown error".to_owned()),
+			)
+		},
+	}
+}
+
+fn report(result: &TestResult) {
+	println!(
+		"test {} ... {}",
+		result.label(),
+		if result.failure.is_none() {
+			"ok"
+		} else {
+			"FAILED"
+		}
+	);
+}
+
+// Renders the results in the JUnit XML format most CI systems understand, a test suite per file.
+fn junit_xml(result_vec: &[TestResult]) -> String {
+	let mut file_path_vec: Vec<&str> = Vec::new();
+
+	for result in result_vec.iter() {
+		if !file_path_vec.contains(&result.file_path.as_str()) {
+			file_path_vec.push(&result.file_path);
+		}
+	}
+
+	let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+	xml += &format!(
+		"<testsuites tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+		result_vec.len(),
+		result_vec
+			.iter()
+			.filter(|result| result.failure.is_some())
+			.count(),
+		result_vec
+			.iter()
+			.map(|result| result.duration.as_secs_f64())
+			.sum::<f64>()
+	);
+
+	for file_path in file_path_vec {
+		let suite_vec = result_vec
+			.iter()
+			.filter(|result| result.file_path == file_path)
+			.collect::<Vec<_>>();
+
+		xml += &format!(
+			"  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+			escape_xml(file_path),
+			suite_vec.len(),
+			suite_vec
+				.iter()
+				.filter(|result| result.failure.is_some())
+				.count(),
+			suite_vec
+				.iter()
+				.map(|result| result.duration.as_secs_f64())
+				.sum::<f64>()
+		);
+
+		for result in suite_vec {
+			xml += &format!(
+				"    <testcase name=\"{}\" classname=\"{}\" file=\"{}\"{} time=\"{:.3}\"",
+				escape_xml(&result.name),
+				escape_xml(file_path),
+				escape_xml(file_path),
+				match result.line_number {
+					Some(line_number) => format!(" line=\"{}\"", line_number),
+					None => String::new(),
+				},
+				result.duration.as_secs_f64()
+			);
+
+			match &result.failure {
+				Some(failure) => {
+					xml += ">\n";
+					xml += &format!(
+						"      <failure message=\"{}\">{}</failure>\n",
+						escape_xml(failure.lines().next().unwrap_or_default()),
+						escape_xml(failure)
+					);
+					xml += "    </testcase>\n";
+				}
+				None => xml += "/>\n",
+			}
+		}
+
+		xml += "  </testsuite>\n";
+	}
+
+	xml += "</testsuites>\n";
+	xml
+}
+
+fn escape_xml(text: &str) -> String {
+	text.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+}
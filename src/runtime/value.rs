@@ -19,6 +19,19 @@ pub enum ValueType {
 	String,
 }
 
+impl ValueType {
+	pub fn from_name(name: &str) -> Option<ValueType> {
+		match name {
+			"Array" => Some(ValueType::Array),
+			"Dictionary" => Some(ValueType::Dictionary),
+			"Bool" => Some(ValueType::Bool),
+			"Integer" => Some(ValueType::Integer),
+			"String" => Some(ValueType::String),
+			_ => None,
+		}
+	}
+}
+
 pub trait FromValue: Sized {
 	fn from_value(value: &Value) -> Option<Self>;
 }
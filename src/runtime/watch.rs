@@ -0,0 +1,105 @@
+use super::execution::Execution;
+use super::imported_pipeline::{ImportError, ImportedPipeline};
+use glob::glob;
+use std::collections::BTreeMap;
+use std::fs::metadata;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::{Duration, SystemTime};
+
+// A failed run must not end the watch, so its panic is caught.
+#[cfg(panic = "abort")]
+compile_error!("the watch mode needs panics to unwind; build with panic = 'unwind'");
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+// Changes are collected until nothing changed for this long, so that saving many files at once
+// (e.g. a checkout) results in a single run.
+const DEBOUNCE_INTERVAL: Duration = Duration::from_millis(300);
+
+// The modification time and length of every watched file; None if it does not exist.
+type Snapshot = BTreeMap<PathBuf, Option<(SystemTime, u64)>>;
+
+// Executes the pipeline again whenever the pipelines it imported or the files matched by its
+// glob() calls change. It never returns; a failed run just waits for the next change.
+pub fn watch(input: &Path, build_execution: impl Fn() -> Execution) -> ! {
+	loop {
+		let execution = Arc::new(build_execution());
+		let mut path_vec = vec![input.canonicalize().unwrap_or_else(|_| input.to_path_buf())];
+		let mut glob_vec = Vec::new();
+
+		match ImportedPipeline::import(input) {
+			Ok(entry_pipeline) => {
+				if catch_unwind(AssertUnwindSafe(|| execution.run(entry_pipeline))).is_err() {
+					eprintln!("The pipeline failed.");
+				}
+
+				path_vec = execution.watched_path_vec();
+				glob_vec = execution.watched_glob_vec();
+			}
+			Err(err @ ImportError::Malformed(..)) => eprintln!("{}", err),
+			Err(err) => {
+				eprintln!("Unable to read the given path: {}", input.display());
+				eprintln!("\tbecause: {}", err);
+			}
+		}
+
+		let mut snapshot = take_snapshot(&path_vec, &glob_vec);
+
+		eprintln!(
+			"Watching {} file{} for changes...",
+			snapshot.len(),
+			if snapshot.len() == 1 { "" } else { "s" }
+		);
+
+		loop {
+			sleep(POLL_INTERVAL);
+
+			let next_snapshot = take_snapshot(&path_vec, &glob_vec);
+
+			if next_snapshot != snapshot {
+				snapshot = next_snapshot;
+				break;
+			}
+		}
+
+		loop {
+			sleep(DEBOUNCE_INTERVAL);
+
+			let next_snapshot = take_snapshot(&path_vec, &glob_vec);
+
+			if next_snapshot == snapshot {
+				break;
+			}
+
+			snapshot = next_snapshot;
+		}
+
+		eprintln!("Changes detected; executing again.");
+	}
+}
+
+fn take_snapshot(path_vec: &[PathBuf], glob_vec: &[String]) -> Snapshot {
+	let mut snapshot = Snapshot::new();
+
+	for path in path_vec.iter() {
+		snapshot.insert(path.clone(), stat(path));
+	}
+
+	for pattern in glob_vec.iter() {
+		if let Ok(paths) = glob(pattern) {
+			for path in paths.flatten() {
+				let status = stat(&path);
+				snapshot.insert(path, status);
+			}
+		}
+	}
+
+	snapshot
+}
+
+fn stat(path: &Path) -> Option<(SystemTime, u64)> {
+	let metadata = metadata(path).ok()?;
+	Some((metadata.modified().ok()?, metadata.len()))
+}